@@ -0,0 +1,157 @@
+use super::model::{Attribute, ComponentsFile, Field, FieldType};
+use crate::diagnostics::Diagnostic;
+use crate::utils::*;
+
+/// Lua type that scripts see when reading a field of type `data_type` through the ffi,
+/// also used for the fields of events
+pub(crate) fn lua_type(data_type: &FieldType) -> String {
+    match data_type {
+        FieldType::Float | FieldType::Double => String::from("number"),
+        FieldType::Bool => String::from("boolean"),
+        FieldType::Vec2 => String::from("Vec2"),
+        FieldType::Vec3 => String::from("Vec3"),
+        FieldType::Vec4 => String::from("Vec4"),
+        FieldType::Rgb => String::from("RGB"),
+        FieldType::Rgba => String::from("RGBA"),
+        FieldType::Array(element, _) => format!("{}[]", lua_type(element)),
+        FieldType::Struct(struct_type) => struct_type.lua_struct_name(),
+        FieldType::ImportedPointer(name) => name.lua_name.clone(),
+        // Char arrays and foreign pointers are plain cdata to Lua
+        FieldType::String(_) | FieldType::Pointer(_) => String::from("ffi.cdata*"),
+        // 64 bit integers are cdata too, but LuaLS treats them like any other integer
+        _ => String::from("integer"),
+    }
+}
+
+/// Comment added to a field annotation, from its description and how to use it
+fn field_comment(field: &Field) -> String {
+    let usage = match &field.data_type {
+        FieldType::String(_) => Some(format!(
+            "read and write it with get_{0} and set_{0}",
+            field.member_name()
+        )),
+        FieldType::Enum(_) => Some(format!("one of the values of {}", field.type_name())),
+        _ if !field.bits.is_empty() => Some(format!("a mask of the bits of {}", field.type_name())),
+        _ => None,
+    };
+    let description = field.description.as_ref().map(|description| {
+        description
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    });
+    match (description, usage) {
+        (Some(description), Some(usage)) => format!(" {} ({})", description, usage),
+        (Some(text), None) | (None, Some(text)) => format!(" {}", text),
+        (None, None) => String::from(""),
+    }
+}
+
+/// A `---@class` annotation for a struct declared in the ffi cdef
+fn generate_class(name: &str, description: &Option<String>, fields: &[Field]) -> String {
+    let mut lines: Vec<String> = description
+        .iter()
+        .flat_map(|description| description.lines())
+        .map(|line| format!("--- {}", line).trim_end().to_string())
+        .collect();
+    lines.push(format!("---@class {}", name));
+    lines.extend(fields.iter().map(|field| {
+        format!(
+            "---@field {} {}{}",
+            field.member_name(),
+            lua_type(&field.data_type),
+            field_comment(field)
+        )
+    }));
+    lines.join("\n")
+}
+
+/// Annotations of the vector and color structs, which are declared by the engine
+fn generate_vector_classes() -> Vec<String> {
+    [
+        ("Vec2", FieldType::Vec2),
+        ("Vec3", FieldType::Vec3),
+        ("Vec4", FieldType::Vec4),
+        ("RGB", FieldType::Rgb),
+        ("RGBA", FieldType::Rgba),
+    ]
+    .iter()
+    .map(|(name, data_type)| {
+        let mut lines = vec![format!("---@class {}", name)];
+        lines.extend(
+            data_type
+                .vector_components()
+                .unwrap()
+                .iter()
+                .map(|component| format!("---@field {} number", component)),
+        );
+        lines.join("\n")
+    })
+    .collect()
+}
+
+/// Key of an entity's component in a `---@field` annotation, quoted unless it is a
+/// plain identifier
+fn entity_key(name: &str) -> String {
+    if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+    {
+        name.to_string()
+    } else {
+        format!("[\"{}\"]", name)
+    }
+}
+
+/// Generates the lua-language-server annotations of the structs declared by the Lua
+/// definitions, and adds the components of the namespace to the `Entity` class so that
+/// `entity.position.y` is typed. `lua_file` names the definitions file, for the header.
+pub fn generate(
+    components: &ComponentsFile,
+    lua_file: &str,
+    template_dirs: &[String],
+) -> Result<String, Diagnostic> {
+    let scripted: Vec<_> = components
+        .components
+        .iter()
+        .filter(|x| !x.has_attribute(Attribute::NoScript))
+        .collect();
+    let mut classes = Vec::new();
+    if components.namespace == "core" {
+        classes.extend(generate_vector_classes());
+    }
+    classes.extend(components.types.iter().map(|struct_type| {
+        generate_class(
+            &struct_type.lua_struct_name(),
+            &struct_type.description,
+            &struct_type.fields,
+        )
+    }));
+    classes.extend(scripted.iter().map(|component| {
+        generate_class(
+            &component.lua_struct_name(),
+            &component.description,
+            &component.fields,
+        )
+    }));
+    if !scripted.is_empty() {
+        let mut entity = vec![String::from("---@class Entity")];
+        entity.extend(scripted.iter().map(|component| {
+            format!(
+                "---@field {} {}",
+                entity_key(&component.qualified_name()),
+                component.lua_struct_name()
+            )
+        }));
+        classes.push(entity.join("\n"));
+    }
+    template_to_str(
+        "components.d.lua",
+        &liquid::object!({
+            "lua_file": lua_file,
+            "classes": classes.join("\n\n"),
+        }),
+        template_dirs,
+    )
+}
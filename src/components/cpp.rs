@@ -0,0 +1,656 @@
+use super::model::{
+    pascal_case, snake_case, Attribute, Component, ComponentsFile, Constraints, DefaultValue,
+    Field, FieldType, OutOfRange,
+};
+use super::{bit_mask, float_literal, string_literal, DATA_TYPES_CPP};
+use crate::diagnostics::Diagnostic;
+use crate::utils::*;
+
+fn cpp_type(data_type: &FieldType) -> &'static str {
+    DATA_TYPES_CPP.types.get(data_type.name()).unwrap()
+}
+
+/// C++ expression converting the `toml::value` named by `value` to the field's type
+fn read_value(data_type: &FieldType, value: &str) -> String {
+    match data_type {
+        FieldType::HashedString => format!(
+            "entt::hashed_string{{intern(toml::get<std::string>({}))}}",
+            value
+        ),
+        // Saved refs and signals are plain hashes, since the name can't be recovered
+        FieldType::Ref | FieldType::Signal => format!(
+            "({value}.is_integer() ? entt::hashed_string::hash_type(toml::get<toml::integer>({value})) : entt::hashed_string::value(toml::get<std::string>({value}).c_str()))",
+            value = value
+        ),
+        FieldType::Resource(resource_type) => format!(
+            "engine->loadResource(\"{}\"_hs, toml::get<std::string>({}).c_str(), 0)",
+            resource_type, value
+        ),
+        FieldType::Vec2 | FieldType::Vec3 | FieldType::Vec4 | FieldType::Rgb | FieldType::Rgba => {
+            format!(
+                "{}{{{}}}",
+                cpp_type(data_type),
+                data_type
+                    .vector_components()
+                    .unwrap()
+                    .iter()
+                    .map(|c| format!("float(as_floating(toml::find({}, \"{}\")))", value, c))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )
+        }
+        FieldType::Float | FieldType::Double => format!(
+            "{}(as_floating({}))",
+            cpp_type(data_type),
+            value
+        ),
+        FieldType::Bool => format!("bool(toml::get<toml::boolean>({}))", value),
+        FieldType::Pointer(_) | FieldType::ImportedPointer(_) => String::from("nullptr"),
+        _ => format!(
+            "{}(toml::get<toml::integer>({}))",
+            cpp_type(data_type),
+            value
+        ),
+    }
+}
+
+/// C++ literal of a range limit, in the type of the values it limits
+fn limit_literal(data_type: &FieldType, limit: f64) -> String {
+    match data_type {
+        FieldType::Double => float_literal(limit, ""),
+        _ if data_type.integer_range().is_some() => format!("{}", limit as i64),
+        _ => float_literal(limit, "f"),
+    }
+}
+
+/// Statements that read the `toml::value` named by `value` into a `toml::integer` named
+/// `integer`, rejecting values that don't fit in the field's type
+fn generate_integer_reader(data_type: &FieldType, value: &str) -> Vec<String> {
+    let (min, max) = data_type.integer_range().unwrap();
+    let outside = [
+        (min > i64::MIN as i128).then(|| format!("integer < {}", min)),
+        (max < i64::MAX as i128).then(|| format!("integer > {}", max)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<String>>();
+    let mut code = vec![format!(
+        "auto integer = toml::get<toml::integer>({});",
+        value
+    )];
+    if !outside.is_empty() {
+        code.push(format!("if ({}) {{", outside.join(" || ")));
+        code.push(format!(
+            "\tthrow std::invalid_argument(toml::format_error(\"value does not fit in {}\", {}, \"expected values {} to {}\"));",
+            data_type.name(),
+            value,
+            min,
+            max
+        ));
+        code.push(String::from("}"));
+    }
+    code
+}
+
+/// Statements that reject or clamp the loaded `target` when it is outside of the field's
+/// range, and normalize it, `value` is the `toml::value` it was loaded from
+fn generate_constraints_check(
+    constraints: &Constraints,
+    data_type: &FieldType,
+    target: &str,
+    value: &str,
+) -> Vec<String> {
+    let mut code = Vec::new();
+    if constraints.has_range() {
+        let (scalars, scalar_type) = match data_type.vector_components() {
+            Some(components) => (
+                components
+                    .iter()
+                    .map(|c| format!("{}.{}", target, c))
+                    .collect::<Vec<String>>(),
+                "float",
+            ),
+            // Integers are checked before being narrowed to the field's type
+            None if data_type.integer_range().is_some() => {
+                (vec![target.to_string()], "toml::integer")
+            }
+            None => (vec![target.to_string()], cpp_type(data_type)),
+        };
+        let min = constraints.min.map(|min| limit_literal(data_type, min));
+        let max = constraints.max.map(|max| limit_literal(data_type, max));
+        let outside = scalars
+            .iter()
+            .flat_map(|scalar| {
+                min.iter()
+                    .map(move |min| format!("{} < {}", scalar, min))
+                    .chain(max.iter().map(move |max| format!("{} > {}", scalar, max)))
+            })
+            .collect::<Vec<String>>()
+            .join(" || ");
+        code.push(format!("if ({}) {{", outside));
+        match constraints.out_of_range {
+            OutOfRange::Reject => code.push(format!(
+                "\tthrow std::invalid_argument(toml::format_error(\"value is out of range\", {}, \"expected values {}\"));",
+                value,
+                constraints.describe_range()
+            )),
+            OutOfRange::Clamp => {
+                code.push(format!(
+                    "\tspdlog::warn(\"{{}}\", toml::format_error(\"value is out of range\", {}, \"clamped to values {}\"));",
+                    value,
+                    constraints.describe_range()
+                ));
+                for scalar in &scalars {
+                    code.push(match (&min, &max) {
+                        (Some(min), Some(max)) => format!(
+                            "\t{} = std::clamp<{}>({}, {}, {});",
+                            scalar, scalar_type, scalar, min, max
+                        ),
+                        (Some(min), None) => format!(
+                            "\t{} = std::max<{}>({}, {});",
+                            scalar, scalar_type, scalar, min
+                        ),
+                        (None, Some(max)) => format!(
+                            "\t{} = std::min<{}>({}, {});",
+                            scalar, scalar_type, scalar, max
+                        ),
+                        (None, None) => unreachable!(),
+                    });
+                }
+            }
+        }
+        code.push(String::from("}"));
+    }
+    if constraints.normalized {
+        code.push(format!("if (glm::length({}) == 0.0f) {{", target));
+        code.push(format!(
+            "\tthrow std::invalid_argument(toml::format_error(\"cannot normalize a zero vector\", {}, \"expected a vector with a length\"));",
+            value
+        ));
+        code.push(String::from("}"));
+        code.push(format!("{} = glm::normalize({});", target, target));
+    }
+    code
+}
+
+/// Statements that load the `toml::value` named by `value` into `target`
+fn generate_value_loader(
+    field: &Field,
+    data_type: &FieldType,
+    target: &str,
+    value: &str,
+) -> Vec<String> {
+    match data_type {
+        FieldType::Array(element, length) => {
+            // Arrays of structs can hold arrays, each loop needs its own index
+            let index = match target.matches('[').count() {
+                0 => String::from("index"),
+                depth => format!("index{}", depth),
+            };
+            let mut code = vec![
+                format!(
+                    "const auto& elements = toml::get<toml::array>({});",
+                    value
+                ),
+                format!("if (elements.size() != {}) {{", length),
+                format!(
+                    "\tthrow std::invalid_argument(toml::format_error(\"wrong number of elements\", {}, \"expected {} elements\"));",
+                    value, length
+                ),
+                String::from("}"),
+                format!(
+                    "for (std::size_t {index} = 0; {index} < {}; ++{index}) {{",
+                    length,
+                    index = index
+                ),
+                format!("\tconst auto& element = elements[{}];", index),
+            ];
+            code.extend(
+                generate_value_loader(
+                    field,
+                    element,
+                    &format!("{}[{}]", target, index),
+                    "element",
+                )
+                    .into_iter()
+                    .map(|line| format!("\t{}", line)),
+            );
+            code.push(String::from("}"));
+            code
+        }
+        FieldType::Struct(struct_type) => {
+            let mut code = vec![format!("const auto& table = {};", value)];
+            code.extend(generate_fields_loader(&struct_type.fields, target));
+            code
+        }
+        FieldType::String(size) => vec![
+            format!("const auto& text = toml::get<std::string>({});", value),
+            format!("if (text.size() >= {}) {{", size),
+            format!(
+                "\tspdlog::warn(\"{{}}\", toml::format_error(\"string is too long\", {}, \"truncated to {} bytes\"));",
+                value,
+                size - 1
+            ),
+            String::from("}"),
+            format!(
+                "const auto length = std::min(text.size(), std::size_t({}));",
+                size - 1
+            ),
+            format!("std::memcpy({}, text.data(), length);", target),
+            format!("{}[length] = '\\0';", target),
+        ],
+        FieldType::Enum(values) => {
+            let mut code = vec![format!(
+                "const auto& name = toml::get<std::string>({});",
+                value
+            )];
+            for (index, variant) in values.iter().enumerate() {
+                code.push(format!(
+                    "{}if (name == \"{}\") {{",
+                    if index == 0 { "" } else { "} else " },
+                    variant
+                ));
+                code.push(format!(
+                    "\t{} = std::decay_t<decltype({})>::{};",
+                    target,
+                    target,
+                    pascal_case(variant)
+                ));
+            }
+            code.push(String::from("} else {"));
+            code.push(format!(
+                "\tthrow std::invalid_argument(toml::format_error(\"unknown value \\\"\" + name + \"\\\"\", {}, \"expected one of: {}\"));",
+                value,
+                values.join(", ")
+            ));
+            code.push(String::from("}"));
+            code
+        }
+        _ if !field.bits.is_empty() => {
+            let mut code = vec![
+                format!("if ({}.is_array()) {{", value),
+                format!("\t{} = {{}};", target),
+                format!(
+                    "\tfor (const auto& bit : toml::get<toml::array>({})) {{",
+                    value
+                ),
+                String::from("\t\tconst auto& name = toml::get<std::string>(bit);"),
+            ];
+            for (position, bit) in field.bits.iter().enumerate() {
+                code.push(format!(
+                    "\t\t{}if (name == \"{}\") {{",
+                    if position == 0 { "" } else { "} else " },
+                    bit
+                ));
+                code.push(format!(
+                    "\t\t\t{} |= {};",
+                    target,
+                    bit_mask(data_type, position)
+                ));
+            }
+            code.push(String::from("\t\t} else {"));
+            code.push(format!(
+                "\t\t\tthrow std::invalid_argument(toml::format_error(\"unknown bit \\\"\" + name + \"\\\"\", bit, \"expected some of: {}\"));",
+                field.bits.join(", ")
+            ));
+            code.push(String::from("\t\t}"));
+            code.push(String::from("\t}"));
+            code.push(String::from("} else {"));
+            code.extend(
+                generate_integer_reader(data_type, value)
+                    .into_iter()
+                    .map(|line| format!("\t{}", line)),
+            );
+            code.push(format!("\t{} = {}(integer);", target, cpp_type(data_type)));
+            code.push(String::from("}"));
+            code
+        }
+        _ if data_type.integer_range().is_some() => {
+            let mut code = generate_integer_reader(data_type, value);
+            code.extend(generate_constraints_check(
+                &field.constraints,
+                data_type,
+                "integer",
+                value,
+            ));
+            code.push(format!("{} = {}(integer);", target, cpp_type(data_type)));
+            code
+        }
+        _ => {
+            let mut code = vec![format!("{} = {};", target, read_value(data_type, value))];
+            code.extend(generate_constraints_check(
+                &field.constraints,
+                data_type,
+                target,
+                value,
+            ));
+            code
+        }
+    }
+}
+
+/// Statements that load a field of `object` from the `toml::value` named `table`
+fn generate_field_loader(field: &Field, object: &str) -> Vec<String> {
+    let target = format!("{}.{}", object, field.member_name());
+    let body = generate_value_loader(field, &field.data_type, &target, "value")
+        .into_iter()
+        .map(|line| format!("\t{}", line));
+    let mut code: Vec<String> = Vec::new();
+    if field.is_required() {
+        code.push(String::from("{"));
+        code.push(format!(
+            "\tconst auto& value = toml::find(table, \"{}\");",
+            field.name
+        ));
+    } else {
+        code.push(format!("if (table.contains(\"{}\")) {{", field.name));
+        code.push(format!(
+            "\tconst auto& value = table.at(\"{}\");",
+            field.name
+        ));
+    }
+    code.extend(body);
+    if let (FieldType::Resource(resource_type), Some(DefaultValue::String(path))) =
+        (&field.data_type, &field.default)
+    {
+        code.push(String::from("} else {"));
+        code.push(format!(
+            "\t{} = engine->loadResource(\"{}\"_hs, {}, 0);",
+            target,
+            resource_type,
+            string_literal(path)
+        ));
+    }
+    code.push(String::from("}"));
+    code
+}
+
+/// Statements that load the fields of `object`, pointers are left as nullptr
+fn generate_fields_loader(fields: &[Field], object: &str) -> Vec<String> {
+    saved_fields(fields)
+        .flat_map(|field| generate_field_loader(field, object))
+        .collect()
+}
+
+/// C++ expression converting the field value `source` to a `toml::value`
+fn write_value(data_type: &FieldType, source: &str) -> String {
+    match data_type {
+        FieldType::Entity => format!(
+            "toml::value(static_cast<toml::integer>(entt::to_integral({})))",
+            source
+        ),
+        FieldType::Byte => format!("toml::value(std::to_integer<toml::integer>({}))", source),
+        FieldType::HashedString => format!(
+            "toml::value(std::string({source}.data() ? {source}.data() : \"\"))",
+            source = source
+        ),
+        FieldType::Resource(_) => format!("toml::value(engine->resourceName({}))", source),
+        FieldType::Vec2 | FieldType::Vec3 | FieldType::Vec4 | FieldType::Rgb | FieldType::Rgba => {
+            format!(
+                "toml::value(toml::table{{{}}})",
+                data_type
+                    .vector_components()
+                    .unwrap()
+                    .iter()
+                    .map(|c| format!("{{\"{}\", toml::value({}.{})}}", c, source, c))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )
+        }
+        FieldType::Float | FieldType::Double => {
+            format!("toml::value(static_cast<toml::floating>({}))", source)
+        }
+        FieldType::Bool => format!("toml::value({})", source),
+        FieldType::String(_) => format!("toml::value(std::string({}))", source),
+        FieldType::Enum(values) => format!(
+            "toml::value([](auto value) {{ switch (value) {{ {} default: return std::string(); }} }}({}))",
+            values
+                .iter()
+                .map(|variant| format!(
+                    "case decltype(value)::{}: return std::string(\"{}\");",
+                    pascal_case(variant),
+                    variant
+                ))
+                .collect::<Vec<String>>()
+                .join(" "),
+            source
+        ),
+        FieldType::Array(element, _) => format!(
+            "toml::value([&](const auto& elements) {{ toml::array array; for (const auto& element : elements) {{ array.push_back({}); }} return array; }}({}))",
+            write_value(element, "element"),
+            source
+        ),
+        FieldType::Struct(struct_type) => format!(
+            "toml::value(toml::table{{{}}})",
+            saved_fields(&struct_type.fields)
+                .map(|field| format!(
+                    "{{\"{}\", {}}}",
+                    field.name,
+                    write_value(&field.data_type, &format!("{}.{}", source, field.member_name()))
+                ))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        _ => format!("toml::value(static_cast<toml::integer>({}))", source),
+    }
+}
+
+/// Fields that are written by savers, pointers only make sense at runtime
+fn saved_fields(fields: &[Field]) -> impl Iterator<Item = &Field> {
+    fields.iter().filter(|field| {
+        !matches!(
+            field.data_type,
+            FieldType::Pointer(_) | FieldType::ImportedPointer(_)
+        )
+    })
+}
+
+/// Statements that save the fields of `object` into the `toml::table` named `values`
+fn generate_fields_saver(fields: &[Field], object: &str) -> Vec<String> {
+    saved_fields(fields)
+        .map(|field| {
+            format!(
+                "values[\"{}\"] = {};",
+                field.name,
+                write_value(
+                    &field.data_type,
+                    &format!("{}.{}", object, field.member_name())
+                )
+            )
+        })
+        .collect()
+}
+
+/// Statement setting up the `component_def` for an attribute
+fn attribute_setting(attribute: &Attribute) -> &'static str {
+    match attribute {
+        Attribute::Tag => "component_def.is_tag = true;",
+        Attribute::Singleton => "component_def.is_singleton = true;",
+        Attribute::EditorHidden => "component_def.editor_hidden = true;",
+        Attribute::NoScript => "component_def.script_accessible = false;",
+    }
+}
+
+/// C++ namespace of the component's struct, relative to `components::`
+pub(super) fn component_namespace(namespace: &str, component: &Component) -> String {
+    let namespace = if namespace.is_empty() {
+        "core"
+    } else {
+        namespace
+    };
+    match &component.namespace_name() {
+        Some(sub_namespace) => format!("{}::{}", namespace, sub_namespace),
+        None => String::from(namespace),
+    }
+}
+
+/// Suffix of the generated load, save, test and inspector functions of a component
+pub(super) fn component_id(component: &Component) -> String {
+    snake_case(&component.qualified_name().replace('/', "-"))
+}
+
+/// Statements that move the value-initialized `target` into the field's range, so that the
+/// round-trip test passes the loader's checks. Members with a default start from it instead
+fn generate_test_value(field: &Field, data_type: &FieldType, target: &str) -> Vec<String> {
+    let constraints = &field.constraints;
+    match data_type {
+        FieldType::Array(element, length) => {
+            let index = match target.matches('[').count() {
+                0 => String::from("index"),
+                depth => format!("index{}", depth),
+            };
+            let body = generate_test_value(field, element, &format!("{}[{}]", target, index));
+            if body.is_empty() {
+                return body;
+            }
+            let mut code = vec![format!(
+                "for (std::size_t {index} = 0; {index} < {}; ++{index}) {{",
+                length,
+                index = index
+            )];
+            code.extend(body.into_iter().map(|line| format!("\t{}", line)));
+            code.push(String::from("}"));
+            code
+        }
+        FieldType::Struct(struct_type) => generate_test_values(&struct_type.fields, target),
+        // The loader rejects zero vectors and saves normalized ones, so start from a unit vector
+        _ if constraints.normalized => match field.default {
+            Some(_) => vec![format!("{} = glm::normalize({});", target, target)],
+            None => vec![format!(
+                "{}.{} = 1.0f;",
+                target,
+                data_type.vector_components().unwrap()[0]
+            )],
+        },
+        _ if field.default.is_some() || constraints.contains(0.0) => Vec::new(),
+        _ => {
+            // Zero is outside of the range, so one of the limits is the closest value to it
+            let limit = limit_literal(
+                data_type,
+                constraints
+                    .min
+                    .filter(|min| *min > 0.0)
+                    .or(constraints.max)
+                    .unwrap(),
+            );
+            match data_type.vector_components() {
+                Some(components) => components
+                    .iter()
+                    .map(|c| format!("{}.{} = {};", target, c, limit))
+                    .collect(),
+                None if *data_type == FieldType::Byte => {
+                    vec![format!("{} = std::byte{{{}}};", target, limit)]
+                }
+                None => vec![format!("{} = {};", target, limit)],
+            }
+        }
+    }
+}
+
+/// Statements that move the fields of `object` into their ranges, for the round-trip test
+fn generate_test_values(fields: &[Field], object: &str) -> Vec<String> {
+    saved_fields(fields)
+        .flat_map(|field| {
+            let target = format!("{}.{}", object, field.member_name());
+            generate_test_value(field, &field.data_type, &target)
+        })
+        .collect()
+}
+
+/// Load, save and round-trip test functions of a component
+fn generate_component_io(
+    namespace: &str,
+    component: &Component,
+    template_dirs: &[String],
+) -> Result<String, Diagnostic> {
+    template_to_str(
+        "component_io.cpp",
+        &liquid::object!({
+            "namespace": component_namespace(namespace, component),
+            "class_name": component.class_name(),
+            "id": component_id(component),
+            "has_fields": !component.fields.is_empty(),
+            "loader": generate_fields_loader(&component.fields, "component").join("\n\t\t"),
+            "saver": generate_fields_saver(&component.fields, "component").join("\n\t\t"),
+            "test_values": generate_test_values(&component.fields, "component").join("\n\t\t\t"),
+        }),
+        template_dirs,
+    )
+}
+
+fn generate_component_def(
+    namespace: &str,
+    component: &Component,
+    template_dirs: &[String],
+) -> Result<String, Diagnostic> {
+    template_to_str(
+        "component_def.cpp",
+        &liquid::object!({
+            "namespace": component_namespace(namespace, component),
+            "component_name": component.qualified_name(),
+            "class_name": component.class_name(),
+            "id": component_id(component),
+            "has_fields": !component.fields.is_empty(),
+            "component_attributes": component
+                .attributes
+                .iter()
+                .map(attribute_setting)
+                .collect::<Vec<&str>>()
+                .join("\n\t\t\t"),
+        }),
+        template_dirs,
+    )
+}
+
+/// Generates the C++ source that registers the components, their accessors
+/// and their TOML loaders and savers with the engine
+pub fn generate(
+    components: &ComponentsFile,
+    template_dirs: &[String],
+) -> Result<String, Diagnostic> {
+    template_to_str(
+        "components.cpp",
+        &liquid::object!({
+            "name": components.namespace,
+            "has_strings": components
+                .fields()
+                .any(|x| matches!(x.data_type, FieldType::String(_))),
+            "has_warnings": components.fields().any(|x| {
+                matches!(x.data_type, FieldType::String(_))
+                    || (x.constraints.has_range() && x.constraints.out_of_range == OutOfRange::Clamp)
+            }),
+            "has_normalized": components.fields().any(|x| x.constraints.normalized),
+            "has_hashed_strings": components
+                .fields()
+                .any(|x| matches!(x.data_type.element_type(), FieldType::HashedString)),
+            "has_floating": components.fields().any(|x| {
+                let element = x.data_type.element_type();
+                matches!(element, FieldType::Float | FieldType::Double)
+                    || element.vector_components().is_some()
+            }),
+            "component_io": components
+                .components
+                .iter()
+                .map(|x| generate_component_io(&components.namespace, x, template_dirs))
+                .collect::<Result<Vec<String>, Diagnostic>>()?
+                .join("\n"),
+            "components": components
+                .components
+                .iter()
+                .map(|x| generate_component_def(&components.namespace, x, template_dirs))
+                .collect::<Result<Vec<String>, Diagnostic>>()?
+                .join("\n"),
+            "component_tests": components
+                .components
+                .iter()
+                .map(|x| format!(
+                    "        if (!test_{}(engine)) {{\n            failed.push_back(\"{}\");\n        }}",
+                    component_id(x),
+                    x.qualified_name()
+                ))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        }),
+        template_dirs,
+    )
+}
@@ -0,0 +1,75 @@
+use super::model::{Component, ComponentsFile, FieldType, StructType};
+use super::{comment, namespaced_components, DATA_TYPES_CPP};
+use crate::diagnostics::Diagnostic;
+use crate::utils::*;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+fn generate_types(foreign_types: &mut BTreeSet<String>, types: &[Arc<StructType>]) -> String {
+    let mut code = String::from("");
+    for struct_type in types {
+        code.push_str(&DATA_TYPES_CPP.generate_struct(
+            foreign_types,
+            false,
+            &struct_type.class_name(),
+            &comment("\t", &struct_type.description),
+            "\t",
+            &struct_type.fields,
+        ));
+        code.push('\n');
+    }
+    code
+}
+
+fn generate_components(foreign_types: &mut BTreeSet<String>, components: &[Component]) -> String {
+    let mut code = String::from("");
+    for component in components.iter().filter(|x| x.namespace.is_none()) {
+        code.push_str(&DATA_TYPES_CPP.generate_struct(
+            foreign_types,
+            false,
+            &component.class_name(),
+            &comment("\t", &component.description),
+            "\t",
+            &component.fields,
+        ));
+        code.push('\n');
+    }
+
+    for (namespace_name, component_list) in namespaced_components(components) {
+        code.push_str(&format!("\n\tnamespace {} {{\n", namespace_name));
+        for component in component_list {
+            code.push_str(&DATA_TYPES_CPP.generate_struct(
+                foreign_types,
+                false,
+                &component.class_name(),
+                &comment("\t\t", &component.description),
+                "\t\t",
+                &component.fields,
+            ));
+            code.push('\n');
+        }
+        code.push_str(&format!("\n\t}} // {}\n", namespace_name));
+    }
+    code
+}
+
+/// Generates the C++ header declaring the component structs
+pub fn generate(
+    components: &ComponentsFile,
+    template_dirs: &[String],
+) -> Result<String, Diagnostic> {
+    let mut foreign_types: BTreeSet<String> = BTreeSet::new();
+    let components_code = generate_types(&mut foreign_types, &components.types)
+        + &generate_components(&mut foreign_types, &components.components);
+    template_to_str(
+        "components.hpp",
+        &liquid::object!({
+            "namespace": components.namespace,
+            "components": components_code,
+            "includes": components.imports.iter().map(|x| format!("#include <components/{}.hpp>\n", x)).collect::<String>(),
+            "has_arrays": components.fields().any(|x| matches!(x.data_type, FieldType::Array(..))),
+            "pointer_declarations": foreign_types.iter().map(|x| format!("struct {};", x)).collect::<Vec<String>>().join("\n")
+        }),
+        template_dirs,
+    )
+}
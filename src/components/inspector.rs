@@ -0,0 +1,325 @@
+use super::cpp::{component_id, component_namespace};
+use super::model::{Attribute, Component, ComponentsFile, Constraints, Field, FieldType};
+use super::{bit_mask, float_literal, string_literal, DATA_TYPES_CPP};
+use crate::diagnostics::Diagnostic;
+use crate::utils::*;
+
+/// ImGui data type of an integer field, for `ImGui::InputScalar`
+fn imgui_data_type(data_type: &FieldType) -> &'static str {
+    match data_type {
+        FieldType::UInt8 | FieldType::Byte | FieldType::Flags8 => "ImGuiDataType_U8",
+        FieldType::UInt16 | FieldType::Flags16 => "ImGuiDataType_U16",
+        FieldType::UInt32 | FieldType::Flags32 => "ImGuiDataType_U32",
+        FieldType::UInt64 | FieldType::Flags64 => "ImGuiDataType_U64",
+        FieldType::Double => "ImGuiDataType_Double",
+        FieldType::Int8 => "ImGuiDataType_S8",
+        FieldType::Int16 => "ImGuiDataType_S16",
+        FieldType::Int32 => "ImGuiDataType_S32",
+        _ => "ImGuiDataType_S64",
+    }
+}
+
+/// Speed, limits and flags arguments of a float drag widget, empty if the field has
+/// no constraints
+fn drag_float_arguments(constraints: &Constraints) -> String {
+    if !constraints.has_range() && constraints.step.is_none() {
+        return String::new();
+    }
+    format!(
+        ", {}, {}, {}, \"%.3f\", {}",
+        float_literal(constraints.step.unwrap_or(1.0), "f"),
+        constraints
+            .min
+            .map_or(String::from("-FLT_MAX"), |min| float_literal(min, "f")),
+        constraints
+            .max
+            .map_or(String::from("FLT_MAX"), |max| float_literal(max, "f")),
+        if constraints.has_range() {
+            "ImGuiSliderFlags_AlwaysClamp"
+        } else {
+            "ImGuiSliderFlags_None"
+        }
+    )
+}
+
+/// A drag widget for an integer or double limited by the field's constraints
+fn generate_scalar_drag(
+    constraints: &Constraints,
+    data_type: &FieldType,
+    target: &str,
+    label: &str,
+) -> Vec<String> {
+    let cpp_type = DATA_TYPES_CPP.types.get(data_type.name()).unwrap();
+    let literal = |limit: f64| match data_type {
+        FieldType::Double => float_literal(limit, ""),
+        FieldType::Byte => format!("std::byte{{{}}}", limit as i64),
+        _ => format!("{}", limit as i64),
+    };
+    let mut code = vec![String::from("{")];
+    for (name, limit) in [("min", constraints.min), ("max", constraints.max)] {
+        if let Some(limit) = limit {
+            code.push(format!(
+                "\tconst {} {} = {};",
+                cpp_type,
+                name,
+                literal(limit)
+            ));
+        }
+    }
+    code.push(format!(
+        "\tImGui::DragScalar({}, {}, &{}, {}, {}, {}, nullptr, {});",
+        label,
+        imgui_data_type(data_type),
+        target,
+        float_literal(constraints.step.unwrap_or(1.0), "f"),
+        if constraints.min.is_some() {
+            "&min"
+        } else {
+            "nullptr"
+        },
+        if constraints.max.is_some() {
+            "&max"
+        } else {
+            "nullptr"
+        },
+        if constraints.has_range() {
+            "ImGuiSliderFlags_AlwaysClamp"
+        } else {
+            "ImGuiSliderFlags_None"
+        }
+    ));
+    code.push(String::from("}"));
+    code
+}
+
+/// Statements drawing an editor for `target`, a value of type `data_type`, labelled by
+/// the `const char*` expression `label`
+fn generate_widget(field: &Field, data_type: &FieldType, target: &str, label: &str) -> Vec<String> {
+    let constraints = &field.constraints;
+    let constrained = constraints.has_range() || constraints.step.is_some();
+    match data_type {
+        FieldType::Float => vec![format!(
+            "ImGui::DragFloat({}, &{}{});",
+            label,
+            target,
+            drag_float_arguments(constraints)
+        )],
+        FieldType::Double if constrained => {
+            generate_scalar_drag(constraints, data_type, target, label)
+        }
+        FieldType::Double => vec![format!("ImGui::InputDouble({}, &{});", label, target)],
+        FieldType::Bool => vec![format!("ImGui::Checkbox({}, &{});", label, target)],
+        FieldType::Vec2 | FieldType::Vec3 | FieldType::Vec4 => {
+            let drag = format!(
+                "ImGui::DragFloat{}({}, glm::value_ptr({}){})",
+                data_type.vector_components().unwrap().len(),
+                label,
+                target,
+                drag_float_arguments(constraints)
+            );
+            if constraints.normalized {
+                vec![
+                    format!("if ({} && glm::length({}) > 0.0f) {{", drag, target),
+                    format!("\t{} = glm::normalize({});", target, target),
+                    String::from("}"),
+                ]
+            } else {
+                vec![format!("{};", drag)]
+            }
+        }
+        FieldType::Rgb | FieldType::Rgba => vec![format!(
+            "ImGui::ColorEdit{}({}, glm::value_ptr({}));",
+            data_type.vector_components().unwrap().len(),
+            label,
+            target
+        )],
+        FieldType::String(size) => vec![format!(
+            "ImGui::InputText({}, {}, {});",
+            label, target, size
+        )],
+        // Entities and hashes can't be edited meaningfully, so they are only shown
+        FieldType::Entity => vec![format!(
+            "ImGui::Text(\"%s: %u\", {}, unsigned(entt::to_integral({})));",
+            label, target
+        )],
+        FieldType::Ref | FieldType::Signal => vec![format!(
+            "ImGui::Text(\"%s: 0x%08x\", {}, unsigned({}));",
+            label, target
+        )],
+        FieldType::HashedString => vec![format!(
+            "ImGui::Text(\"%s: %s\", {}, {target}.data() ? {target}.data() : \"\");",
+            label,
+            target = target
+        )],
+        FieldType::Resource(_) => vec![format!(
+            "ImGui::Text(\"%s: %s\", {}, engine->resourceName({}).c_str());",
+            label, target
+        )],
+        FieldType::Pointer(_) | FieldType::ImportedPointer(_) => vec![format!(
+            "ImGui::Text(\"%s: %p\", {}, static_cast<const void*>({}));",
+            label, target
+        )],
+        FieldType::Enum(values) => vec![
+            String::from("{"),
+            format!(
+                "\tconst char* items[] = {{{}}};",
+                values
+                    .iter()
+                    .map(|value| string_literal(value))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            format!("\tint current = static_cast<int>({});", target),
+            format!(
+                "\tif (ImGui::Combo({}, &current, items, IM_ARRAYSIZE(items))) {{",
+                label
+            ),
+            format!(
+                "\t\t{} = static_cast<std::decay_t<decltype({})>>(current);",
+                target, target
+            ),
+            String::from("\t}"),
+            String::from("}"),
+        ],
+        FieldType::Array(element, length) => {
+            // Arrays of structs can hold arrays, each loop needs its own index
+            let index = match target.matches('[').count() {
+                0 => String::from("index"),
+                depth => format!("index{}", depth),
+            };
+            let mut code = vec![
+                format!("if (ImGui::TreeNode({})) {{", label),
+                format!(
+                    "\tfor (std::size_t {index} = 0; {index} < {}; ++{index}) {{",
+                    length,
+                    index = index
+                ),
+                format!(
+                    "\t\tconst auto label_{index} = std::to_string({index});",
+                    index = index
+                ),
+            ];
+            code.extend(
+                generate_widget(
+                    field,
+                    element,
+                    &format!("{}[{}]", target, index),
+                    &format!("label_{}.c_str()", index),
+                )
+                .into_iter()
+                .map(|line| format!("\t\t{}", line)),
+            );
+            code.push(String::from("\t}"));
+            code.push(String::from("\tImGui::TreePop();"));
+            code.push(String::from("}"));
+            code
+        }
+        FieldType::Struct(struct_type) => {
+            let mut code = vec![format!("if (ImGui::TreeNode({})) {{", label)];
+            code.extend(
+                generate_widgets(&struct_type.fields, target)
+                    .into_iter()
+                    .map(|line| format!("\t{}", line)),
+            );
+            code.push(String::from("\tImGui::TreePop();"));
+            code.push(String::from("}"));
+            code
+        }
+        _ if !field.bits.is_empty() => {
+            let mut code = vec![format!("if (ImGui::TreeNode({})) {{", label)];
+            for (position, bit) in field.bits.iter().enumerate() {
+                code.extend([
+                    String::from("\t{"),
+                    format!(
+                        "\t\tbool set = ({} & {}) != 0;",
+                        target,
+                        bit_mask(data_type, position)
+                    ),
+                    format!("\t\tif (ImGui::Checkbox({}, &set)) {{", string_literal(bit)),
+                    format!("\t\t\t{} ^= {};", target, bit_mask(data_type, position)),
+                    String::from("\t\t}"),
+                    String::from("\t}"),
+                ]);
+            }
+            code.push(String::from("\tImGui::TreePop();"));
+            code.push(String::from("}"));
+            code
+        }
+        _ if constrained => generate_scalar_drag(constraints, data_type, target, label),
+        _ => vec![format!(
+            "ImGui::InputScalar({}, {}, &{});",
+            label,
+            imgui_data_type(data_type),
+            target
+        )],
+    }
+}
+
+/// Statements drawing an editor for each field of `object`
+fn generate_widgets(fields: &[Field], object: &str) -> Vec<String> {
+    fields
+        .iter()
+        .flat_map(|field| {
+            generate_widget(
+                field,
+                &field.data_type,
+                &format!("{}.{}", object, field.member_name()),
+                &string_literal(&field.name),
+            )
+        })
+        .collect()
+}
+
+fn generate_inspector(
+    namespace: &str,
+    component: &Component,
+    template_dirs: &[String],
+) -> Result<String, Diagnostic> {
+    template_to_str(
+        "component_inspector.cpp",
+        &liquid::object!({
+            "namespace": component_namespace(namespace, component),
+            "class_name": component.class_name(),
+            "id": component_id(component),
+            "widgets": generate_widgets(&component.fields, "component").join("\n\t\t"),
+        }),
+        template_dirs,
+    )
+}
+
+/// Generates the C++ source of the ImGui editors of the components, and the function
+/// registering them with the engine. Components that are hidden from the editor, or that
+/// have no fields to edit, get no inspector.
+pub fn generate(
+    components: &ComponentsFile,
+    template_dirs: &[String],
+) -> Result<String, Diagnostic> {
+    let inspected: Vec<&Component> = components
+        .components
+        .iter()
+        .filter(|x| !x.has_attribute(Attribute::EditorHidden) && !x.fields.is_empty())
+        .collect();
+    template_to_str(
+        "inspectors.cpp",
+        &liquid::object!({
+            "name": components.namespace,
+            "inspectors": inspected
+                .iter()
+                .map(|x| generate_inspector(&components.namespace, x, template_dirs))
+                .collect::<Result<Vec<String>, Diagnostic>>()?
+                .join("\n"),
+            "registrations": inspected
+                .iter()
+                .map(|x| format!(
+                    "\t\tengine->registerInspector<components::{}::{}>(\"{}\"_hs, &inspect_{});",
+                    component_namespace(&components.namespace, x),
+                    x.class_name(),
+                    x.qualified_name(),
+                    component_id(x)
+                ))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        }),
+        template_dirs,
+    )
+}
@@ -0,0 +1,321 @@
+use super::model::{ComponentsFile, Field, FieldType};
+use super::{Types, DATA_TYPES_CPP, DATA_TYPES_LUA};
+use crate::diagnostics::Diagnostics;
+use colored::*;
+use phf::phf_map;
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
+/// Size and alignment of the C++ types that fields are declared with, in bytes
+static CPP_LAYOUTS: phf::Map<&'static str, (usize, usize)> = phf_map! {
+    "entt::entity" => (4, 4),
+    "std::uint8_t" => (1, 1),
+    "std::uint16_t" => (2, 2),
+    "std::uint32_t" => (4, 4),
+    "std::uint64_t" => (8, 8),
+    "std::int8_t" => (1, 1),
+    "std::int16_t" => (2, 2),
+    "std::int32_t" => (4, 4),
+    "std::int64_t" => (8, 8),
+    "std::byte" => (1, 1),
+    "entt::hashed_string::hash_type" => (4, 4),
+    // A pointer to the string and its hash
+    "entt::hashed_string" => (16, 8),
+    "glm::vec2" => (8, 4),
+    "glm::vec3" => (12, 4),
+    "glm::vec4" => (16, 4),
+    "million::resources::Handle" => (4, 4),
+    "float" => (4, 4),
+    "double" => (8, 8),
+    "bool" => (1, 1),
+    "char" => (1, 1),
+};
+
+/// Size and alignment of the LuaJIT ffi types that fields are declared with, in bytes
+static LUA_LAYOUTS: phf::Map<&'static str, (usize, usize)> = phf_map! {
+    "uint8_t" => (1, 1),
+    "uint16_t" => (2, 2),
+    "uint32_t" => (4, 4),
+    "uint64_t" => (8, 8),
+    "int8_t" => (1, 1),
+    "int16_t" => (2, 2),
+    "int32_t" => (4, 4),
+    "int64_t" => (8, 8),
+    "struct Vec2" => (8, 4),
+    "struct Vec3" => (12, 4),
+    "struct Vec4" => (16, 4),
+    "struct RGB" => (12, 4),
+    "struct RGBA" => (16, 4),
+    "float" => (4, 4),
+    "double" => (8, 8),
+    "bool" => (1, 1),
+    "char" => (1, 1),
+};
+
+const POINTER_LAYOUT: (usize, usize) = (8, 8);
+
+/// Where a field is placed in a struct
+struct FieldLayout {
+    type_name: String,
+    offset: usize,
+    size: usize,
+    align: usize,
+}
+
+/// The placement of the fields of a struct under one of the type mappings
+struct StructLayout {
+    fields: Vec<FieldLayout>,
+    size: usize,
+    align: usize,
+}
+
+impl StructLayout {
+    /// Bytes of the struct that do not belong to any field
+    fn padding(&self) -> usize {
+        self.size - self.fields.iter().map(|x| x.size).sum::<usize>()
+    }
+}
+
+fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+/// Computes struct layouts using the C rules, which both C++ and LuaJIT follow
+struct Mapping {
+    types: &'static Types,
+    layouts: &'static phf::Map<&'static str, (usize, usize)>,
+}
+
+static CPP_MAPPING: Mapping = Mapping {
+    types: &DATA_TYPES_CPP,
+    layouts: &CPP_LAYOUTS,
+};
+static LUA_MAPPING: Mapping = Mapping {
+    types: &DATA_TYPES_LUA,
+    layouts: &LUA_LAYOUTS,
+};
+
+impl Mapping {
+    /// Size and alignment of a field type, or the name of the type if it is not known
+    fn type_layout(&self, data_type: &FieldType) -> Result<(usize, usize), String> {
+        match data_type {
+            FieldType::Array(element, length) => self
+                .type_layout(element)
+                .map(|(size, align)| (size * length, align)),
+            FieldType::String(size) => Ok((*size, 1)),
+            FieldType::Struct(struct_type) => self
+                .struct_layout(&struct_type.fields)
+                .map(|layout| (layout.size, layout.align)),
+            FieldType::Pointer(_) | FieldType::ImportedPointer(_) => Ok(POINTER_LAYOUT),
+            _ => {
+                let type_name = self
+                    .types
+                    .types
+                    .get(data_type.storage_type().name())
+                    .unwrap();
+                self.layouts
+                    .get(type_name)
+                    .copied()
+                    .ok_or_else(|| type_name.to_string())
+            }
+        }
+    }
+
+    fn struct_layout(&self, fields: &[Field]) -> Result<StructLayout, String> {
+        let mut layouts = Vec::new();
+        let mut offset = 0;
+        let mut struct_align = 1;
+        for field in fields {
+            let (size, align) = self.type_layout(&field.data_type)?;
+            offset = align_to(offset, align);
+            layouts.push(FieldLayout {
+                type_name: format!(
+                    "{}{}",
+                    self.types
+                        .type_name(&mut BTreeSet::new(), true, field, &field.data_type),
+                    match &field.data_type {
+                        FieldType::Array(_, length) if !self.types.cpp => format!("[{}]", length),
+                        FieldType::String(size) => format!("[{}]", size),
+                        _ => String::from(""),
+                    }
+                ),
+                offset,
+                size,
+                align,
+            });
+            offset += size;
+            struct_align = struct_align.max(align);
+        }
+        Ok(StructLayout {
+            fields: layouts,
+            size: align_to(offset, struct_align),
+            align: struct_align,
+        })
+    }
+}
+
+/// A field order that needs less padding than `fields`, with the bytes it saves
+fn better_order<'a>(fields: &'a [Field], layout: &StructLayout) -> Option<(Vec<&'a Field>, usize)> {
+    let mut order: Vec<(&Field, usize)> = fields
+        .iter()
+        .zip(&layout.fields)
+        .map(|(field, field_layout)| (field, field_layout.align))
+        .collect();
+    // Placing the most aligned fields first leaves padding only at the end
+    order.sort_by_key(|(_, align)| Reverse(*align));
+    let reordered: Vec<Field> = order.iter().map(|(field, _)| (*field).clone()).collect();
+    let size = CPP_MAPPING.struct_layout(&reordered).ok()?.size;
+    (size < layout.size).then(|| {
+        (
+            order.into_iter().map(|(field, _)| field).collect(),
+            layout.size - size,
+        )
+    })
+}
+
+/// Prints the layout of a struct and reports the fields whose C++ and Lua layouts differ.
+/// `table` is the location of the struct's `[[component]]` or `[[type]]` table.
+fn check_struct(
+    diagnostics: &mut Diagnostics,
+    table: &str,
+    index: usize,
+    name: &str,
+    fields: &[Field],
+) {
+    let location = diagnostics.locator.array_table(table, index);
+    let field_location = |diagnostics: &Diagnostics, field: &Field| {
+        diagnostics
+            .locator
+            .key(Some(location), &field.name)
+            .or(Some(location))
+    };
+    let (cpp, lua) = match (
+        CPP_MAPPING.struct_layout(fields),
+        LUA_MAPPING.struct_layout(fields),
+    ) {
+        (Ok(cpp), Ok(lua)) => (cpp, lua),
+        (Err(type_name), _) | (_, Err(type_name)) => {
+            let field = fields
+                .iter()
+                .find(|field| {
+                    [&CPP_MAPPING, &LUA_MAPPING]
+                        .iter()
+                        .any(|mapping| mapping.type_layout(&field.data_type).is_err())
+                })
+                .unwrap();
+            let location = field_location(diagnostics, field);
+            diagnostics.error(
+                location,
+                format!(
+                    "field \"{}\" of {} has the unknown type {}",
+                    field.name, name, type_name
+                ),
+                Some(String::from(
+                    "the type tables map it to a type without a known layout",
+                )),
+            );
+            return;
+        }
+    };
+
+    println!("{}", name.bold());
+    println!(
+        "  {:>6} {:>6} {:>5}  {:<20} {:<32} Lua type",
+        "offset", "size", "align", "field", "C++ type"
+    );
+    let mut mismatched = false;
+    for ((field, cpp_field), lua_field) in fields.iter().zip(&cpp.fields).zip(&lua.fields) {
+        let line = format!(
+            "  {:>6} {:>6} {:>5}  {:<20} {:<32} {}",
+            cpp_field.offset,
+            cpp_field.size,
+            cpp_field.align,
+            field.name,
+            cpp_field.type_name,
+            lua_field.type_name
+        );
+        let matches = cpp_field.offset == lua_field.offset && cpp_field.size == lua_field.size;
+        println!("{}", if matches { line.normal() } else { line.red() });
+        // Fields after the first mismatch are usually only moved by it
+        if !matches && !mismatched {
+            mismatched = true;
+            let location = field_location(diagnostics, field);
+            diagnostics.error(
+                location,
+                format!(
+                    "field \"{}\" of {} has a different layout in C++ and Lua",
+                    field.name, name
+                ),
+                Some(format!(
+                    "C++ declares it as {} ({} bytes at offset {}), Lua as {} ({} bytes at offset {})",
+                    cpp_field.type_name,
+                    cpp_field.size,
+                    cpp_field.offset,
+                    lua_field.type_name,
+                    lua_field.size,
+                    lua_field.offset
+                )),
+            );
+        }
+    }
+    println!(
+        "  size {}, align {}, {} bytes of padding",
+        cpp.size,
+        cpp.align,
+        cpp.padding()
+    );
+    if cpp.size != lua.size {
+        diagnostics.error(
+            Some(location),
+            format!(
+                "{} is {} bytes in C++ but {} bytes in Lua",
+                name, cpp.size, lua.size
+            ),
+            None,
+        );
+    }
+    if let Some((order, saved)) = better_order(fields, &cpp) {
+        println!(
+            "  {} ordering the fields as {} saves {} byte{}",
+            "suggestion:".cyan().bold(),
+            order
+                .iter()
+                .map(|field| field.name.as_str())
+                .collect::<Vec<&str>>()
+                .join(", "),
+            saved,
+            if saved == 1 { "" } else { "s" }
+        );
+    }
+    println!();
+}
+
+/// Prints the memory layout of every struct generated for `components` and reports
+/// where the C++ and Lua declarations disagree. Returns false if they do.
+pub fn check(diagnostics: &mut Diagnostics, components: &ComponentsFile) -> bool {
+    for (index, struct_type) in components.types.iter().enumerate() {
+        check_struct(
+            diagnostics,
+            "type",
+            index,
+            &struct_type.cpp_name(),
+            &struct_type.fields,
+        );
+    }
+    // Tags are never read as structs, and empty structs differ in size between C++ and C
+    for (index, component) in components
+        .components
+        .iter()
+        .enumerate()
+        .filter(|(_, component)| !component.fields.is_empty())
+    {
+        check_struct(
+            diagnostics,
+            "component",
+            index,
+            &component.cpp_name(&components.namespace),
+            &component.fields,
+        );
+    }
+    !diagnostics.has_errors()
+}
@@ -0,0 +1,328 @@
+use super::model::{snake_case, Attribute, Component, ComponentsFile, FieldType};
+use super::{bit_mask, comment, namespaced_components, DATA_TYPES_LUA};
+use crate::diagnostics::Diagnostic;
+use crate::utils::*;
+use std::collections::BTreeSet;
+
+fn generate_components(components: &ComponentsFile) -> String {
+    let mut foreign_types: BTreeSet<String> = BTreeSet::new();
+    let mut code = String::from("");
+    for struct_type in &components.types {
+        code.push_str(&DATA_TYPES_LUA.generate_struct(
+            &mut foreign_types,
+            true,
+            &struct_type.lua_struct_name(),
+            &comment("\t", &struct_type.description),
+            "\t",
+            &struct_type.fields,
+        ));
+    }
+    for component in components
+        .components
+        .iter()
+        .filter(|x| x.namespace.is_none())
+    {
+        code.push_str(&DATA_TYPES_LUA.generate_struct(
+            &mut foreign_types,
+            true,
+            &component.lua_struct_name(),
+            &comment("\t", &component.description),
+            "\t",
+            &component.fields,
+        ));
+    }
+
+    let component_map = namespaced_components(&components.components);
+    if !component_map.is_empty() {
+        code.push('\n');
+        for (_, component_list) in component_map {
+            for component in component_list {
+                code.push_str(&DATA_TYPES_LUA.generate_struct(
+                    &mut foreign_types,
+                    true,
+                    &component.lua_struct_name(),
+                    &comment("\t", &component.description),
+                    "\t",
+                    &component.fields,
+                ));
+            }
+            code.push('\n');
+        }
+    }
+    code
+}
+
+fn generate_component_map(components: &[Component]) -> String {
+    components
+        .iter()
+        .map(|component| {
+            format!(
+                "\t['{}'] = 'struct {}*',",
+                component.qualified_name(),
+                component.lua_struct_name()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+const LUA_KEYWORDS: [&str; 22] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Table key for `name`, quoted if it is a Lua keyword
+fn lua_key(name: &str) -> String {
+    if LUA_KEYWORDS.contains(&name) {
+        format!("['{}']", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Metatype of a vector type, with arithmetic, length and a few helper methods
+fn generate_vector_metatype(name: &str, components: &[&str]) -> String {
+    let each = |f: &dyn Fn(&str) -> String, separator: &str| {
+        components
+            .iter()
+            .map(|c| f(c))
+            .collect::<Vec<String>>()
+            .join(separator)
+    };
+    let length = format!(
+        "math.sqrt({})",
+        each(&|c| format!("a.{c} * a.{c}", c = c), " + ")
+    );
+    [
+        format!("{} = ffi.metatype('struct {}', {{", name, name),
+        format!(
+            "\t__add = function(a, b) return {}({}) end,",
+            name,
+            each(&|c| format!("a.{c} + b.{c}", c = c), ", ")
+        ),
+        format!(
+            "\t__sub = function(a, b) return {}({}) end,",
+            name,
+            each(&|c| format!("a.{c} - b.{c}", c = c), ", ")
+        ),
+        String::from("\t__mul = function(a, b)"),
+        format!(
+            "\t\tif type(a) == 'number' then return {}({}) end",
+            name,
+            each(&|c| format!("a * b.{}", c), ", ")
+        ),
+        format!(
+            "\t\tif type(b) == 'number' then return {}({}) end",
+            name,
+            each(&|c| format!("a.{} * b", c), ", ")
+        ),
+        format!(
+            "\t\treturn {}({})",
+            name,
+            each(&|c| format!("a.{c} * b.{c}", c = c), ", ")
+        ),
+        String::from("\tend,"),
+        String::from("\t__div = function(a, b)"),
+        format!(
+            "\t\tif type(b) == 'number' then return {}({}) end",
+            name,
+            each(&|c| format!("a.{} / b", c), ", ")
+        ),
+        format!(
+            "\t\treturn {}({})",
+            name,
+            each(&|c| format!("a.{c} / b.{c}", c = c), ", ")
+        ),
+        String::from("\tend,"),
+        format!(
+            "\t__unm = function(a) return {}({}) end,",
+            name,
+            each(&|c| format!("-a.{}", c), ", ")
+        ),
+        format!(
+            "\t__eq = function(a, b) return ffi.istype({name}, a) and ffi.istype({name}, b) and {} end,",
+            each(&|c| format!("a.{c} == b.{c}", c = c), " and "),
+            name = name
+        ),
+        format!("\t__len = function(a) return {} end,", length),
+        format!(
+            "\t__tostring = function(a) return string.format('{}({})', {}) end,",
+            name,
+            each(&|_| String::from("%g"), ", "),
+            each(&|c| format!("a.{}", c), ", ")
+        ),
+        String::from("\t__index = {"),
+        format!(
+            "\t\tdot = function(a, b) return {} end,",
+            each(&|c| format!("a.{c} * b.{c}", c = c), " + ")
+        ),
+        format!("\t\tlength = function(a) return {} end,", length),
+        String::from("\t\tnormalized = function(a) return a / #a end,"),
+        String::from("\t},"),
+        String::from("})"),
+    ]
+    .join("\n")
+}
+
+/// Lua code setting up the metatypes shared by the components of a namespace
+fn generate_metatypes(components: &ComponentsFile) -> String {
+    let mut code = String::from("");
+    if components.namespace == "core" {
+        code.push_str("local Vec2, Vec3, Vec4\n");
+        for (name, data_type) in [
+            ("Vec2", FieldType::Vec2),
+            ("Vec3", FieldType::Vec3),
+            ("Vec4", FieldType::Vec4),
+        ] {
+            code.push_str(&generate_vector_metatype(
+                name,
+                data_type.vector_components().unwrap(),
+            ));
+            code.push('\n');
+        }
+    }
+    let has_arrays = components
+        .components
+        .iter()
+        .flat_map(|x| &x.fields)
+        .any(|x| matches!(x.data_type, FieldType::Array(..)));
+    if has_arrays {
+        code.push_str(
+            "local function array_tostring(array, length)
+\tlocal values = {}
+\tfor index = 0, length - 1 do values[#values + 1] = tostring(array[index]) end
+\treturn '{ ' .. table.concat(values, ', ') .. ' }'
+end
+",
+        );
+    }
+    code
+}
+
+/// Constructor of a component, whose metatype gives it a readable `tostring`
+fn generate_constructor(component: &Component) -> String {
+    let mut text = format!("'{} {{", component.qualified_name());
+    for (index, field) in component.fields.iter().enumerate() {
+        let member = format!("component.{}", field.member_name());
+        text.push_str(&format!(
+            "{} {} = ' .. {} .. '",
+            if index == 0 { "" } else { "," },
+            field.member_name(),
+            match &field.data_type {
+                FieldType::String(_) => format!("string.format('%q', ffi.string({}))", member),
+                FieldType::Array(_, length) => format!("array_tostring({}, {})", member, length),
+                _ => format!("tostring({})", member),
+            }
+        ));
+    }
+    text.push_str(if component.fields.is_empty() {
+        "}'"
+    } else {
+        " }'"
+    });
+    format!(
+        "\t\tnew = ffi.metatype('struct {}', {{\n\t\t\t__tostring = function(component) return {} end,\n\t\t}}),",
+        component.lua_struct_name(),
+        text
+    )
+}
+
+/// Lua table of the constants of a component, eg the values of its enum fields
+/// and the masks of named flags bits, and of the accessors of its string fields.
+/// With `metatypes`, the table also holds the constructor of the component.
+fn generate_component_table(component: &Component, metatypes: bool) -> Option<String> {
+    let constructor = if metatypes {
+        Some(generate_constructor(component))
+    } else {
+        None
+    };
+    let entries: Vec<String> = constructor
+        .into_iter()
+        .chain(component.fields.iter().filter_map(|field| match field.data_type.element_type() {
+            FieldType::Enum(values) => Some(format!(
+                "\t\t{} = {{ {} }},",
+                field.type_name(),
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(index, value)| format!("{} = {}", lua_key(&snake_case(value)), index))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )),
+            data_type if !field.bits.is_empty() => Some(format!(
+                "\t\t{} = {{ {} }},",
+                field.type_name(),
+                field
+                    .bits
+                    .iter()
+                    .enumerate()
+                    .map(|(position, bit)| format!(
+                        "{} = {}{}",
+                        lua_key(&snake_case(bit)),
+                        bit_mask(data_type, position),
+                        // Lua numbers are doubles, so the upper bits are 64 bit integer cdata
+                        if position >= 32 { "ULL" } else { "" }
+                    ))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )),
+            _ => None,
+        }))
+        .chain(component.fields.iter().filter_map(|field| match field.data_type {
+            FieldType::String(size) => Some(format!(
+                "\t\tget_{name} = function(component) return ffi.string(component.{name}) end,\n\t\tset_{name} = function(component, value) ffi.copy(component.{name}, value:sub(1, {length})) end,",
+                name = field.member_name(),
+                length = size - 1
+            )),
+            _ => None,
+        }))
+        .collect();
+    if entries.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "\t['{}'] = {{\n{}\n\t}},",
+            component.qualified_name(),
+            entries.join("\n")
+        ))
+    }
+}
+
+/// Generates the Lua ffi definitions for the components and registers them with the engine.
+/// Components with the `no-script` attribute are left out. With `metatypes`, the components
+/// get constructors and the core namespace also sets up vector arithmetic.
+pub fn generate(
+    components: &ComponentsFile,
+    metatypes: bool,
+    template_dirs: &[String],
+) -> Result<String, Diagnostic> {
+    let components = &ComponentsFile {
+        components: components
+            .components
+            .iter()
+            .filter(|x| !x.has_attribute(Attribute::NoScript))
+            .cloned()
+            .collect(),
+        ..components.clone()
+    };
+    template_to_str(
+        "components.lua",
+        &liquid::object!({
+            "cdef": generate_components(components),
+            "metatypes": if metatypes { generate_metatypes(components) } else { String::from("") },
+            "component_map": generate_component_map(&components.components),
+            "component_tables": components
+                .components
+                .iter()
+                .filter_map(|x| generate_component_table(x, metatypes))
+                .chain(if metatypes && components.namespace == "core" {
+                    vec![String::from("\tVec2 = Vec2,\n\tVec3 = Vec3,\n\tVec4 = Vec4,")]
+                } else {
+                    vec![]
+                })
+                .collect::<Vec<String>>()
+                .join("\n"),
+        }),
+        template_dirs,
+    )
+}
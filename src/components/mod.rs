@@ -0,0 +1,909 @@
+use crate::diagnostics::{Diagnostic, Diagnostics, Location};
+use crate::utils::*;
+use colored::*;
+use model::{pascal_case, Component, ComponentsFile, DefaultValue, Field, FieldType};
+use phf::phf_map;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use toml_edit::Document;
+use walkdir::WalkDir;
+
+mod annotations;
+mod cpp;
+mod hpp;
+mod inspector;
+mod layout;
+mod lua;
+pub mod model;
+mod schema;
+
+pub(crate) use annotations::lua_type;
+
+/// Settings shared by the commands that generate code from components files
+#[derive(Default)]
+pub struct Options {
+    /// Components files, and directories to search for them, that imports are looked up in
+    pub import_path: Vec<String>,
+    /// Give the Lua definitions metatypes, for constructors and vector arithmetic
+    pub metatypes: bool,
+    /// Also generate the ImGui inspectors when generating every file
+    pub inspectors: bool,
+    /// Compare the generated code with the files on disk instead of writing it
+    pub check: bool,
+    /// Directories searched, in order, for templates that replace the embedded ones
+    pub template_dirs: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GeneratorType {
+    HeaderFile,
+    LuaDefinitions,
+    CppDefinitions,
+    Inspectors,
+}
+
+/// The components declared inside of a sub-namespace, grouped by namespace in the order
+/// each namespace is first used, so that generated code does not change from run to run
+fn namespaced_components(components: &[Component]) -> Vec<(String, Vec<&Component>)> {
+    let mut groups: Vec<(String, Vec<&Component>)> = Vec::new();
+    for component in components {
+        if let Some(namespace_name) = component.namespace_name() {
+            match groups.iter_mut().find(|(name, _)| *name == namespace_name) {
+                Some((_, group)) => group.push(component),
+                None => groups.push((namespace_name, vec![component])),
+            }
+        }
+    }
+    groups
+}
+
+/// A `//` comment line for each line of a description, which both C++ and the
+/// LuaJIT cdef parser accept
+fn comment(indent: &str, description: &Option<String>) -> String {
+    match description {
+        Some(description) => description
+            .lines()
+            .map(|line| format!("{}// {}\n", indent, line).replace(" \n", "\n"))
+            .collect(),
+        None => String::from(""),
+    }
+}
+
+struct Types {
+    types: phf::Map<&'static str, &'static str>,
+    /// C++ structs can declare nested types and give their members default values
+    cpp: bool,
+}
+
+static DATA_TYPES_CPP: Types = Types {
+    types: phf_map! {
+        "entity" => "entt::entity",
+        "uint8" => "std::uint8_t",
+        "uint16" => "std::uint16_t",
+        "uint32" => "std::uint32_t",
+        "uint64" => "std::uint64_t",
+        "int8" => "std::int8_t",
+        "int16" => "std::int16_t",
+        "int32" => "std::int32_t",
+        "int64" => "std::int64_t",
+        "byte" => "std::byte",
+        "flags8" => "std::uint8_t",
+        "flags16" => "std::uint16_t",
+        "flags32" => "std::uint32_t",
+        "flags64" => "std::uint64_t",
+        "ref" => "entt::hashed_string::hash_type",
+        "signal" => "entt::hashed_string::hash_type",
+        "hashed-string" => "entt::hashed_string",
+        "vec3" => "glm::vec3",
+        "vec4" => "glm::vec4",
+        "vec2" => "glm::vec2",
+        "resource" => "million::resources::Handle",
+        "float" => "float",
+        "double" => "double",
+        "bool" => "bool",
+        "rgb" => "glm::vec3",
+        "rgba" => "glm::vec4",
+        "string" => "char",
+    },
+    cpp: true,
+};
+static DATA_TYPES_LUA: Types = Types {
+    types: phf_map! {
+        "entity" => "uint32_t",
+        "uint8" => "uint8_t",
+        "uint16" => "uint16_t",
+        "uint32" => "uint32_t",
+        "uint64" => "uint64_t",
+        "int8" => "int8_t",
+        "int16" => "int16_t",
+        "int32" => "int32_t",
+        "int64" => "int64_t",
+        "byte" => "uint8_t",
+        "flags8" => "uint8_t",
+        "flags16" => "uint16_t",
+        "flags32" => "uint32_t",
+        "flags64" => "uint64_t",
+        "ref" => "uint32_t",
+        "signal" => "uint32_t",
+        "hashed-string" => "uint32_t",
+        "vec2" => "struct Vec2",
+        "vec3" => "struct Vec3",
+        "vec4" => "struct Vec4",
+        "resource" => "uint32_t",
+        "float" => "float",
+        "double" => "double",
+        "bool" => "bool",
+        "rgb" => "struct RGB",
+        "rgba" => "struct RGBA",
+        "string" => "char",
+    },
+    cpp: false,
+};
+
+impl Types {
+    fn type_name(
+        &self,
+        foreign_types: &mut BTreeSet<String>,
+        include_specifier: bool,
+        field: &Field,
+        data_type: &FieldType,
+    ) -> String {
+        match data_type {
+            FieldType::Enum(_) if self.cpp => field.type_name(),
+            FieldType::Array(element, length) if self.cpp => format!(
+                "std::array<{}, {}>",
+                self.type_name(foreign_types, include_specifier, field, element),
+                length
+            ),
+            // C arrays are declared with the length after the member name
+            FieldType::Array(element, _) => {
+                self.type_name(foreign_types, include_specifier, field, element)
+            }
+            FieldType::Struct(struct_type) if self.cpp => struct_type.cpp_name(),
+            FieldType::ImportedPointer(name) if self.cpp => format!("{}*", name.cpp_name),
+            FieldType::ImportedPointer(name) => format!("struct {}*", name.lua_name),
+            FieldType::Struct(struct_type) => format!("struct {}", struct_type.lua_struct_name()),
+            FieldType::Pointer(type_name) => {
+                foreign_types.insert(type_name.to_string());
+                format!(
+                    "{}{}*",
+                    if include_specifier { "struct " } else { "" },
+                    type_name
+                )
+            }
+            _ => self
+                .types
+                .get(data_type.storage_type().name())
+                .unwrap()
+                .to_string(),
+        }
+    }
+
+    /// Declarations of the types nested inside of a struct, eg the enum class of enum fields
+    fn generate_nested_types(&self, indent: &str, fields: &[Field]) -> Vec<String> {
+        if !self.cpp {
+            return Vec::new();
+        }
+        fields
+            .iter()
+            .filter_map(|field| match field.data_type.element_type() {
+                FieldType::Enum(values) => Some(format!(
+                    "{}\tenum class {} : {} {{ {} }};",
+                    indent,
+                    field.type_name(),
+                    self.types
+                        .get(field.data_type.element_type().storage_type().name())
+                        .unwrap(),
+                    values
+                        .iter()
+                        .map(|value| pascal_case(value))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )),
+                data_type if !field.bits.is_empty() => Some(format!(
+                    "{}\tstruct {} {{ static constexpr {} {}; }};",
+                    indent,
+                    field.type_name(),
+                    self.types.get(data_type.name()).unwrap(),
+                    field
+                        .bits
+                        .iter()
+                        .enumerate()
+                        .map(|(position, bit)| format!(
+                            "{} = {}",
+                            pascal_case(bit),
+                            bit_mask(data_type, position)
+                        ))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn generate_component_field(
+        &self,
+        indent: &str,
+        foreign_types: &mut BTreeSet<String>,
+        include_specifier: bool,
+        field: &Field,
+    ) -> String {
+        format!(
+            "{}{}\t{} {}{}{};",
+            comment(&format!("{}\t", indent), &field.description),
+            indent,
+            self.type_name(foreign_types, include_specifier, field, &field.data_type),
+            field.member_name(),
+            match &field.data_type {
+                FieldType::Array(_, length) if !self.cpp => format!("[{}]", length),
+                FieldType::String(size) => format!("[{}]", size),
+                _ => String::from(""),
+            },
+            match cpp_literal(field) {
+                Some(literal) if self.cpp => format!("{{{}}}", literal),
+                _ => String::from(""),
+            }
+        )
+    }
+
+    fn generate_struct(
+        &self,
+        foreign_types: &mut BTreeSet<String>,
+        include_specifier: bool,
+        struct_name: &str,
+        description: &str,
+        indent: &str,
+        fields: &[Field],
+    ) -> String {
+        format!(
+            "\n{}{}struct {} {{\n{}\n{}}};",
+            description,
+            indent,
+            struct_name,
+            self.generate_nested_types(indent, fields)
+                .into_iter()
+                .chain(fields.iter().map(|x| self.generate_component_field(
+                    indent,
+                    foreign_types,
+                    include_specifier,
+                    x
+                )))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            indent,
+        )
+    }
+}
+
+/// Hexadecimal mask of a bit in a flags field, padded to the width of the field
+fn bit_mask(data_type: &FieldType, position: usize) -> String {
+    let width = data_type.flags_width().unwrap_or(64);
+    format!("{:#0digits$x}", 1u64 << position, digits = width / 4 + 2)
+}
+
+fn string_literal(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\t', "\\t")
+    )
+}
+
+fn float_literal(value: f64, suffix: &str) -> String {
+    let literal = format!("{:?}", value);
+    if literal.contains(['.', 'e']) {
+        format!("{}{}", literal, suffix)
+    } else {
+        format!("{}.0{}", literal, suffix)
+    }
+}
+
+/// C++ initializer for the default value of a field, without the outer braces.
+/// Resources have no literal form, they are loaded through the engine.
+fn cpp_literal(field: &Field) -> Option<String> {
+    cpp_initializer(field, &field.data_type, field.default.as_ref()?)
+}
+
+fn cpp_initializer(field: &Field, data_type: &FieldType, default: &DefaultValue) -> Option<String> {
+    let suffix = if *data_type == FieldType::Float {
+        "f"
+    } else {
+        ""
+    };
+    match (data_type, default) {
+        (FieldType::Resource(_), _) => None,
+        (FieldType::Array(element, _), DefaultValue::Array(values)) => Some(format!(
+            "{{{}}}",
+            values
+                .iter()
+                .map(|value| match value {
+                    DefaultValue::Vector(_) => cpp_initializer(field, element, value)
+                        .map(|initializer| format!("{{{}}}", initializer)),
+                    _ => cpp_initializer(field, element, value),
+                })
+                .collect::<Option<Vec<String>>>()?
+                .join(", ")
+        )),
+        (FieldType::Enum(_), DefaultValue::String(value)) => {
+            Some(format!("{}::{}", field.type_name(), pascal_case(value)))
+        }
+        (FieldType::Byte, DefaultValue::Integer(value)) => Some(format!("std::byte{{{}}}", value)),
+        (FieldType::UInt64 | FieldType::Flags64, DefaultValue::Integer(value)) => {
+            Some(format!("{}ull", *value as u64))
+        }
+        (FieldType::Int64, DefaultValue::Integer(value)) => Some(format!("{}ll", value)),
+        (_, DefaultValue::Integer(value)) => Some(value.to_string()),
+        (_, DefaultValue::Float(value)) => Some(float_literal(*value, suffix)),
+        (_, DefaultValue::Bool(value)) => Some(value.to_string()),
+        (FieldType::HashedString | FieldType::String(_), DefaultValue::String(value)) => {
+            Some(string_literal(value))
+        }
+        (_, DefaultValue::String(value)) => Some(format!(
+            "entt::hashed_string::value({})",
+            string_literal(value)
+        )),
+        (_, DefaultValue::Array(_)) => None,
+        (_, DefaultValue::Vector(values)) => Some(
+            values
+                .iter()
+                .map(|value| float_literal(*value, "f"))
+                .collect::<Vec<String>>()
+                .join(", "),
+        ),
+    }
+}
+
+pub fn toml_error_hint(error: &toml_edit::TomlError) -> Option<String> {
+    let hint = error
+        .to_string()
+        .lines()
+        .skip(1)
+        .filter(|line| !line.contains('|'))
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join(", ");
+    if hint.is_empty() {
+        None
+    } else {
+        Some(hint)
+    }
+}
+
+/// Paths of the components files found at `path`, searching directories recursively
+fn components_files(path: &str) -> Vec<String> {
+    if Path::new(path).is_dir() {
+        let mut files: Vec<String> = WalkDir::new(path)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| entry.file_name() == "components.toml")
+            .map(|entry| entry.path().display().to_string())
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_string()]
+    }
+}
+
+/// Key identifying a file no matter how its path was written
+fn file_key(path: &str) -> String {
+    fs::canonicalize(path)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// Loads components files, along with the files of the namespaces they import
+struct Importer {
+    /// The components file declaring each namespace found on the import path
+    paths: HashMap<String, String>,
+    /// Files already loaded, `None` if they had errors
+    loaded: HashMap<String, Option<Arc<ComponentsFile>>>,
+    /// Files currently being loaded, to detect import cycles
+    loading: Vec<String>,
+    /// Problems found in the files loaded so far, in the order they were loaded
+    errors: Vec<Diagnostic>,
+}
+
+impl Importer {
+    /// `import_path` lists the components files and directories that imports are looked up in
+    fn new(import_path: &[String]) -> Importer {
+        let mut paths = HashMap::new();
+        for file in import_path.iter().flat_map(|path| components_files(path)) {
+            let namespace = fs::read_to_string(&file)
+                .ok()
+                .and_then(|text| text.parse::<Document>().ok())
+                .and_then(|defs| defs.get("namespace")?.as_str().map(String::from));
+            if let Some(namespace) = namespace {
+                paths.entry(namespace).or_insert(file);
+            }
+        }
+        Importer {
+            paths,
+            loaded: HashMap::new(),
+            loading: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Reads and checks a components file, collecting any problems found in `errors`.
+    /// Returns `None` if there were any.
+    fn load(&mut self, source: &str) -> Option<Arc<ComponentsFile>> {
+        let key = file_key(source);
+        if let Some(components) = self.loaded.get(&key) {
+            return components.clone();
+        }
+        let text = match fs::read_to_string(source) {
+            Ok(text) => text,
+            Err(error) => {
+                let mut diagnostics = Diagnostics::new(source, "");
+                diagnostics.error(
+                    None,
+                    format!("could not read components file: {}", error),
+                    None,
+                );
+                self.errors.append(&mut diagnostics.errors);
+                self.loaded.insert(key, None);
+                return None;
+            }
+        };
+        let mut diagnostics = Diagnostics::new(source, &text);
+        let components = match text.parse::<Document>() {
+            Ok(defs) => {
+                self.loading.push(key.clone());
+                let imports = self.load_imports(&mut diagnostics, &defs);
+                self.loading.pop();
+                // The fields of a file cannot be checked without the namespaces it imports
+                imports
+                    .and_then(|imports| model::parse(&mut diagnostics, &defs, &imports))
+                    .map(Arc::new)
+            }
+            Err(error) => {
+                diagnostics.error(
+                    error
+                        .line_col()
+                        .map(|(line, column)| Location { line, column }),
+                    String::from("components file is not valid TOML"),
+                    toml_error_hint(&error),
+                );
+                None
+            }
+        };
+        self.errors.append(&mut diagnostics.errors);
+        self.loaded.insert(key, components.clone());
+        components
+    }
+
+    /// Loads the files of the namespaces imported by `defs`. Returns `None`, after collecting
+    /// their problems, if any of them could not be loaded.
+    fn load_imports(
+        &mut self,
+        diagnostics: &mut Diagnostics,
+        defs: &Document,
+    ) -> Option<Vec<Arc<ComponentsFile>>> {
+        let location = diagnostics
+            .locator
+            .key(None, "imports")
+            .map(|k| diagnostics.locator.value(k));
+        let mut imports = Vec::new();
+        for namespace in model::parse_imports(diagnostics, defs)? {
+            let path = match self.paths.get(&namespace) {
+                Some(path) => path.clone(),
+                None => {
+                    diagnostics.error(
+                        location,
+                        format!("could not find namespace \"{}\" to import", namespace),
+                        Some(String::from(
+                            "add the directory containing its components.toml with -I <directory>",
+                        )),
+                    );
+                    return None;
+                }
+            };
+            if self.loading.contains(&file_key(&path)) {
+                diagnostics.error(
+                    location,
+                    format!(
+                        "namespace \"{}\" ({}) already imports this file",
+                        namespace, path
+                    ),
+                    Some(String::from(
+                        "namespaces cannot import each other in a cycle",
+                    )),
+                );
+                return None;
+            }
+            match self.load(&path) {
+                Some(import) => imports.push(import),
+                None => {
+                    diagnostics.error(
+                        location,
+                        format!(
+                            "could not import namespace \"{}\" because {} has errors",
+                            namespace, path
+                        ),
+                        None,
+                    );
+                    return None;
+                }
+            }
+        }
+        Some(imports)
+    }
+}
+
+/// Name of the file generated for a namespace, without its extension
+fn file_stem(namespace: &str, generate_what: GeneratorType) -> String {
+    match generate_what {
+        GeneratorType::Inspectors => format!("{}_inspectors", namespace),
+        GeneratorType::HeaderFile => namespace.to_string(),
+        _ if namespace == "core" => String::from("core_components"),
+        _ => namespace.to_string(),
+    }
+}
+
+/// The files generated for a namespace by `generate_what`
+fn namespace_files(
+    components: &ComponentsFile,
+    generate_what: GeneratorType,
+    options: &Options,
+) -> Result<Vec<GeneratedFile>, Diagnostic> {
+    let stem = file_stem(&components.namespace, generate_what);
+    let file = |extension: &str, contents: String| GeneratedFile {
+        name: format!("{}.{}", stem, extension),
+        contents,
+    };
+    Ok(match generate_what {
+        GeneratorType::LuaDefinitions => vec![
+            file(
+                "lua",
+                lua::generate(components, options.metatypes, &options.template_dirs)?,
+            ),
+            file(
+                "d.lua",
+                annotations::generate(
+                    components,
+                    &format!("{}.lua", stem),
+                    &options.template_dirs,
+                )?,
+            ),
+        ],
+        GeneratorType::HeaderFile => vec![file(
+            "hpp",
+            hpp::generate(components, &options.template_dirs)?,
+        )],
+        GeneratorType::CppDefinitions => vec![file(
+            "cpp",
+            cpp::generate(components, &options.template_dirs)?,
+        )],
+        GeneratorType::Inspectors => vec![file(
+            "cpp",
+            inspector::generate(components, &options.template_dirs)?,
+        )],
+    })
+}
+
+/// Generates the files of `generate_what` for the components file `source`, without
+/// writing them. Imports are looked up in the import path.
+pub fn generate_files(source: &str, generate_what: GeneratorType, options: &Options) -> Generated {
+    let mut importer = Importer::new(&options.import_path);
+    match importer.load(source) {
+        Some(components) => namespace_files(&components, generate_what, options).into(),
+        None => Generated::failed(importer.errors),
+    }
+}
+
+/// Generates the files of `generate_what` for the components file `source` into
+/// `output_dir`, returning the problems found instead if there are any
+pub fn generate(
+    source: &str,
+    generate_what: GeneratorType,
+    output_dir: &str,
+    options: &Options,
+) -> Result<(), Vec<Diagnostic>> {
+    let generated = generate_files(source, generate_what, options);
+    if !generated.is_ok() {
+        return Err(generated.diagnostics);
+    }
+    let files = generated.files;
+
+    if options.check {
+        let changes = files
+            .iter()
+            .map(|file| {
+                let path = output_path(output_dir, &file.name);
+                (check_file_with_str(&path, &file.contents), path)
+            })
+            .collect::<Vec<_>>();
+        return report_check(&changes, output_dir).map_err(|error| vec![error]);
+    }
+    for file in files {
+        let path = output_path(output_dir, &file.name);
+        let description = match generate_what {
+            GeneratorType::LuaDefinitions if file.name.ends_with(".d.lua") => "Lua annotations",
+            GeneratorType::LuaDefinitions => "Lua definition",
+            GeneratorType::HeaderFile => "C++ header file",
+            GeneratorType::CppDefinitions => "C++ definition",
+            GeneratorType::Inspectors => "C++ inspectors",
+        };
+        println!("Outputting {} to: {}", description, path.blue());
+        update_file_with_str(&path, &file.contents).map_err(|error| vec![error])?;
+    }
+    Ok(())
+}
+
+/// Writes the generated files into `output_dir`, or with `--check` only compares them,
+/// returning what writing did or would do to each file
+fn write_outputs(
+    files: &[GeneratedFile],
+    output_dir: &str,
+    options: &Options,
+) -> Result<Vec<(FileStatus, String)>, Diagnostic> {
+    if !options.check {
+        make_directory(output_dir)?;
+    }
+    files
+        .iter()
+        .map(|file| {
+            let path = output_path(output_dir, &file.name);
+            let status = if options.check {
+                check_file_with_str(&path, &file.contents)
+            } else {
+                update_file_with_str(&path, &file.contents)?
+            };
+            Ok((status, path))
+        })
+        .collect()
+}
+
+/// Lists the generated files that are out of date, returning an error if there are any
+fn report_check(changes: &[(FileStatus, String)], output_dir: &str) -> Result<(), Diagnostic> {
+    let mut stale = 0;
+    for (status, path) in changes {
+        match status {
+            FileStatus::Created => println!("  {} {}", "missing".red(), path),
+            FileStatus::Updated => println!("  {} {}", "out of date".red(), path),
+            FileStatus::Unchanged => continue,
+        }
+        stale += 1;
+    }
+    if stale > 0 {
+        let mut diagnostics = Diagnostics::new(output_dir, "");
+        diagnostics.error(
+            None,
+            format!(
+                "{} of {} generated files would change",
+                stale,
+                changes.len()
+            ),
+            Some(String::from(
+                "run the command without --check to update them",
+            )),
+        );
+        return Err(diagnostics.errors.pop().unwrap());
+    }
+    println!(
+        "All generated files are up to date ({} checked)",
+        changes.len()
+    );
+    Ok(())
+}
+
+/// The `components.toml` files named by `sources`, searching directories recursively,
+/// and an error for each directory that did not contain one
+fn find_components_files(sources: &[String]) -> (Vec<String>, Vec<Diagnostic>) {
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    for source in sources {
+        let mut entries = components_files(source);
+        if entries.is_empty() {
+            let mut diagnostics = Diagnostics::new(source, "");
+            diagnostics.error(
+                None,
+                String::from("directory does not contain any components.toml files"),
+                None,
+            );
+            errors.append(&mut diagnostics.errors);
+        }
+        files.append(&mut entries);
+    }
+    (files, errors)
+}
+
+/// An error for every namespace declared by more than one of `files`
+fn check_namespaces(files: &[(String, Arc<ComponentsFile>)]) -> Vec<Diagnostic> {
+    let mut namespaces: HashMap<&str, &str> = HashMap::new();
+    let mut errors = Vec::new();
+    for (source, components) in files {
+        if let Some(other) = namespaces.insert(&components.namespace, source) {
+            let text = fs::read_to_string(source).unwrap_or_default();
+            let mut diagnostics = Diagnostics::new(source, &text);
+            let location = diagnostics
+                .locator
+                .key(None, "namespace")
+                .map(|k| diagnostics.locator.value(k));
+            diagnostics.error(
+                location,
+                format!(
+                    "namespace \"{}\" is already declared by {}",
+                    components.namespace, other
+                ),
+                Some(String::from("each components file needs its own namespace")),
+            );
+            errors.append(&mut diagnostics.errors);
+        }
+    }
+    errors
+}
+
+/// Loads every components file in `sources`, which can be files or directories, returning
+/// the problems found if any of them has errors. Imports are looked up in `sources` and
+/// in the import path.
+pub fn load_all(
+    sources: &[String],
+    options: &Options,
+) -> Result<Vec<(String, Arc<ComponentsFile>)>, Vec<Diagnostic>> {
+    let (files, mut errors) = find_components_files(sources);
+    let mut importer = Importer::new(&[sources, &options.import_path].concat());
+    let loaded: Vec<(String, Arc<ComponentsFile>)> = files
+        .iter()
+        .filter_map(|source| {
+            importer
+                .load(source)
+                .map(|components| (source.clone(), components))
+        })
+        .collect();
+    errors.append(&mut importer.errors);
+    if errors.is_empty() {
+        errors = check_namespaces(&loaded);
+    }
+    if errors.is_empty() {
+        Ok(loaded)
+    } else {
+        Err(errors)
+    }
+}
+
+/// The header, Lua and C++ files of every loaded namespace
+fn all_files(
+    loaded: &[(String, Arc<ComponentsFile>)],
+    options: &Options,
+) -> Result<Vec<GeneratedFile>, Diagnostic> {
+    let mut generators = vec![
+        GeneratorType::HeaderFile,
+        GeneratorType::LuaDefinitions,
+        GeneratorType::CppDefinitions,
+    ];
+    if options.inspectors {
+        generators.push(GeneratorType::Inspectors);
+    }
+    let mut files = Vec::new();
+    for (_, components) in loaded {
+        for generate_what in &generators {
+            files.append(&mut namespace_files(components, *generate_what, options)?);
+        }
+    }
+    Ok(files)
+}
+
+/// Generates the header, Lua and C++ files for every components file in `sources`, which
+/// can be files or directories, without writing them. Imports are looked up in `sources`
+/// and in the import path.
+pub fn generate_all_files(sources: &[String], options: &Options) -> Generated {
+    match load_all(sources, options) {
+        Ok(loaded) => all_files(&loaded, options).into(),
+        Err(errors) => Generated::failed(errors),
+    }
+}
+
+/// Generates the header, Lua and C++ files for every components file in `sources`,
+/// which can be files or directories, and prints a summary of the files that changed.
+/// Imports are looked up in `sources` and in the import path.
+pub fn generate_all(
+    sources: &[String],
+    output_dir: &str,
+    options: &Options,
+) -> Result<(), Vec<Diagnostic>> {
+    let loaded = load_all(sources, options)?;
+    let files = all_files(&loaded, options).map_err(|error| vec![error])?;
+    let changes = write_outputs(&files, output_dir, options).map_err(|error| vec![error])?;
+
+    if options.check {
+        return report_check(&changes, output_dir).map_err(|error| vec![error]);
+    }
+    for (status, path) in &changes {
+        match status {
+            FileStatus::Created => println!("  {} {}", "created".green(), path),
+            FileStatus::Updated => println!("  {} {}", "updated".yellow(), path),
+            FileStatus::Unchanged => (),
+        }
+    }
+    let count = |status| changes.iter().filter(|(s, _)| *s == status).count();
+    println!(
+        "Generated {} files from {} components file{}: {} created, {} updated, {} unchanged",
+        changes.len(),
+        loaded.len(),
+        if loaded.len() == 1 { "" } else { "s" },
+        count(FileStatus::Created),
+        count(FileStatus::Updated),
+        count(FileStatus::Unchanged)
+    );
+    Ok(())
+}
+
+/// The entity and scene schemas of the loaded namespaces
+fn schema_files(loaded: Vec<(String, Arc<ComponentsFile>)>) -> Vec<GeneratedFile> {
+    let files: Vec<Arc<ComponentsFile>> = loaded
+        .into_iter()
+        .map(|(_, components)| components)
+        .collect();
+    let (entity, scene) = schema::generate(&files);
+    vec![
+        GeneratedFile {
+            name: String::from("entity.schema.json"),
+            contents: entity,
+        },
+        GeneratedFile {
+            name: String::from("scene.schema.json"),
+            contents: scene,
+        },
+    ]
+}
+
+/// Generates the JSON Schemas of entity and scene files for the components declared in
+/// `sources`, without writing them
+pub fn generate_schema_files(sources: &[String], options: &Options) -> Generated {
+    match load_all(sources, options) {
+        Ok(loaded) => Generated::new(schema_files(loaded)),
+        Err(errors) => Generated::failed(errors),
+    }
+}
+
+/// Generates JSON Schemas for the entity and scene files that can use the components
+/// declared in `sources`, so that editors can complete and check them
+pub fn generate_schema(
+    sources: &[String],
+    output_dir: &str,
+    options: &Options,
+) -> Result<(), Vec<Diagnostic>> {
+    let files = schema_files(load_all(sources, options)?);
+    let changes = write_outputs(&files, output_dir, options).map_err(|error| vec![error])?;
+
+    if options.check {
+        return report_check(&changes, output_dir).map_err(|error| vec![error]);
+    }
+    for (status, path) in &changes {
+        match status {
+            FileStatus::Created => println!("  {} {}", "created".green(), path),
+            FileStatus::Updated => println!("  {} {}", "updated".yellow(), path),
+            FileStatus::Unchanged => println!("  {} {}", "unchanged".dimmed(), path),
+        }
+    }
+    Ok(())
+}
+
+/// Prints the memory layout of the structs generated for the components files in
+/// `sources` and checks that the C++ and Lua declarations agree, returning the
+/// differences if they don't
+pub fn check_layout(sources: &[String], options: &Options) -> Result<(), Vec<Diagnostic>> {
+    let mut errors = Vec::new();
+    for (source, components) in load_all(sources, options)? {
+        let text = fs::read_to_string(&source).unwrap_or_default();
+        let mut diagnostics = Diagnostics::new(&source, &text);
+        if !layout::check(&mut diagnostics, &components) {
+            errors.append(&mut diagnostics.errors);
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
@@ -0,0 +1,2519 @@
+use crate::diagnostics::{Diagnostics, Location};
+use case_style::CaseStyle;
+use phf::phf_map;
+use std::sync::Arc;
+use toml_edit::{Document, Item, Table, TableLike, Value};
+
+/// The resolved type of a component field
+#[derive(Clone, PartialEq, Debug)]
+pub enum FieldType {
+    Entity,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Byte,
+    Flags8,
+    Flags16,
+    Flags32,
+    Flags64,
+    Ref,
+    Signal,
+    HashedString,
+    Vec2,
+    Vec3,
+    Vec4,
+    Rgb,
+    Rgba,
+    Float,
+    Double,
+    Bool,
+    /// `resource:<resource-type>`
+    Resource(String),
+    /// `ptr:<type-name>`, a pointer to a type that is not managed by the engine
+    Pointer(String),
+    /// `ptr:<namespace>.<name>`, a pointer to a component or type of an imported namespace
+    ImportedPointer(ImportedName),
+    /// `{ type = "enum", values = [...] }`, stored as the smallest unsigned integer that fits
+    Enum(Vec<String>),
+    /// `array:<type>:<length>` or `{ type = "array", of = "<type>", len = <length> }`
+    Array(Box<FieldType>, usize),
+    /// `string:<size>`, a NUL terminated char buffer of `size` bytes
+    String(usize),
+    /// A `[[type]]` declared earlier in the file, referred to by its name
+    Struct(Arc<StructType>),
+}
+
+/// Longest fixed-size array a field can hold
+const MAX_ARRAY_LENGTH: usize = u16::MAX as usize;
+/// Largest char buffer a string field can hold, including the NUL terminator
+const MAX_STRING_SIZE: usize = u16::MAX as usize;
+
+static BUILTIN_TYPES: phf::Map<&'static str, FieldType> = phf_map! {
+    "entity" => FieldType::Entity,
+    "uint8" => FieldType::UInt8,
+    "uint16" => FieldType::UInt16,
+    "uint32" => FieldType::UInt32,
+    "uint64" => FieldType::UInt64,
+    "int8" => FieldType::Int8,
+    "int16" => FieldType::Int16,
+    "int32" => FieldType::Int32,
+    "int64" => FieldType::Int64,
+    "byte" => FieldType::Byte,
+    "flags8" => FieldType::Flags8,
+    "flags16" => FieldType::Flags16,
+    "flags32" => FieldType::Flags32,
+    "flags64" => FieldType::Flags64,
+    "ref" => FieldType::Ref,
+    "signal" => FieldType::Signal,
+    "hashed-string" => FieldType::HashedString,
+    "vec2" => FieldType::Vec2,
+    "vec3" => FieldType::Vec3,
+    "vec4" => FieldType::Vec4,
+    "float" => FieldType::Float,
+    "double" => FieldType::Double,
+    "bool" => FieldType::Bool,
+    "rgb" => FieldType::Rgb,
+    "rgba" => FieldType::Rgba,
+};
+
+impl FieldType {
+    /// The built in type called `name`, eg "vec3"
+    pub fn builtin(name: &str) -> Option<FieldType> {
+        BUILTIN_TYPES.get(name).cloned()
+    }
+
+    /// The name used for this type in the type tables of the generators
+    pub fn name(&self) -> &'static str {
+        match self {
+            FieldType::Entity => "entity",
+            FieldType::UInt8 => "uint8",
+            FieldType::UInt16 => "uint16",
+            FieldType::UInt32 => "uint32",
+            FieldType::UInt64 => "uint64",
+            FieldType::Int8 => "int8",
+            FieldType::Int16 => "int16",
+            FieldType::Int32 => "int32",
+            FieldType::Int64 => "int64",
+            FieldType::Byte => "byte",
+            FieldType::Flags8 => "flags8",
+            FieldType::Flags16 => "flags16",
+            FieldType::Flags32 => "flags32",
+            FieldType::Flags64 => "flags64",
+            FieldType::Ref => "ref",
+            FieldType::Signal => "signal",
+            FieldType::HashedString => "hashed-string",
+            FieldType::Vec2 => "vec2",
+            FieldType::Vec3 => "vec3",
+            FieldType::Vec4 => "vec4",
+            FieldType::Rgb => "rgb",
+            FieldType::Rgba => "rgba",
+            FieldType::Float => "float",
+            FieldType::Double => "double",
+            FieldType::Bool => "bool",
+            FieldType::Resource(_) => "resource",
+            FieldType::Pointer(_) | FieldType::ImportedPointer(_) => "ptr",
+            FieldType::Enum(_) => "enum",
+            FieldType::Array(_, _) => "array",
+            FieldType::String(_) => "string",
+            FieldType::Struct(_) => "struct",
+        }
+    }
+
+    /// The type of the elements of arrays, or the type itself for anything else
+    pub fn element_type(&self) -> &FieldType {
+        match self {
+            FieldType::Array(element, _) => element,
+            _ => self,
+        }
+    }
+
+    /// The integer type used to store values of enum types
+    pub fn storage_type(&self) -> &FieldType {
+        match self {
+            FieldType::Enum(values) if values.len() > u8::MAX as usize + 1 => &FieldType::UInt16,
+            FieldType::Enum(_) => &FieldType::UInt8,
+            _ => self,
+        }
+    }
+
+    /// Names of the components of vector and color types, in memory order
+    pub fn vector_components(&self) -> Option<&'static [&'static str]> {
+        match self {
+            FieldType::Vec2 => Some(&["x", "y"]),
+            FieldType::Vec3 => Some(&["x", "y", "z"]),
+            FieldType::Vec4 => Some(&["x", "y", "z", "w"]),
+            FieldType::Rgb => Some(&["r", "g", "b"]),
+            FieldType::Rgba => Some(&["r", "g", "b", "a"]),
+            _ => None,
+        }
+    }
+
+    /// Number of bits in a flags type
+    pub fn flags_width(&self) -> Option<usize> {
+        match self {
+            FieldType::Flags8 => Some(8),
+            FieldType::Flags16 => Some(16),
+            FieldType::Flags32 => Some(32),
+            FieldType::Flags64 => Some(64),
+            _ => None,
+        }
+    }
+
+    /// Whether `min`, `max` and `step` can limit values of the type, or each component
+    /// of its vectors
+    pub fn is_numeric(&self) -> bool {
+        match self {
+            FieldType::Float | FieldType::Double => true,
+            FieldType::Entity => false,
+            _ => {
+                self.vector_components().is_some()
+                    || (self.integer_range().is_some() && self.flags_width().is_none())
+            }
+        }
+    }
+
+    /// Whether content values can be converted to the type by a change-type migration
+    pub fn is_scalar(&self) -> bool {
+        match self {
+            FieldType::Float
+            | FieldType::Double
+            | FieldType::Bool
+            | FieldType::Ref
+            | FieldType::Signal
+            | FieldType::HashedString
+            | FieldType::Resource(_)
+            | FieldType::String(_) => true,
+            _ => self.integer_range().is_some(),
+        }
+    }
+
+    /// Smallest and largest values that fit in an integer type
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        match self {
+            FieldType::Entity => Some((0, u32::MAX as i128)),
+            FieldType::UInt8 | FieldType::Byte | FieldType::Flags8 => Some((0, u8::MAX as i128)),
+            FieldType::UInt16 | FieldType::Flags16 => Some((0, u16::MAX as i128)),
+            FieldType::UInt32 | FieldType::Flags32 => Some((0, u32::MAX as i128)),
+            FieldType::UInt64 | FieldType::Flags64 => Some((0, u64::MAX as i128)),
+            FieldType::Int8 => Some((i8::MIN as i128, i8::MAX as i128)),
+            FieldType::Int16 => Some((i16::MIN as i128, i16::MAX as i128)),
+            FieldType::Int32 => Some((i32::MIN as i128, i32::MAX as i128)),
+            FieldType::Int64 => Some((i64::MIN as i128, i64::MAX as i128)),
+            _ => None,
+        }
+    }
+
+    /// Parses a type name, returning a hint describing the problem on failure
+    fn parse(
+        field_name: &str,
+        data_type: &str,
+        scope: &Scope,
+    ) -> Result<FieldType, (String, String)> {
+        if let Some(type_name) = data_type.strip_prefix("ptr:") {
+            if type_name.is_empty() {
+                Err((
+                    format!("field \"{}\" is a pointer without a type name", field_name),
+                    String::from("use ptr:<type-name>, eg \"ptr:b2Body\""),
+                ))
+            } else if let Some((namespace, name)) = type_name.split_once('.') {
+                scope.imported_pointer(field_name, namespace, name)
+            } else {
+                Ok(FieldType::Pointer(type_name.to_string()))
+            }
+        } else if let Some(resource_type) = data_type.strip_prefix("resource:") {
+            if resource_type.is_empty() {
+                Err((
+                    format!(
+                        "field \"{}\" is a resource without a resource type",
+                        field_name
+                    ),
+                    String::from("use resource:<resource-type>, eg \"resource:mesh\""),
+                ))
+            } else {
+                Ok(FieldType::Resource(resource_type.to_string()))
+            }
+        } else if let Some(array) = data_type.strip_prefix("array:") {
+            let hint = || String::from("use array:<type>:<length>, eg \"array:float:4\"");
+            let (element, length) = array.rsplit_once(':').ok_or_else(|| {
+                (
+                    format!("array field \"{}\" does not specify its length", field_name),
+                    hint(),
+                )
+            })?;
+            let length = parse_array_length(field_name, length.parse::<i64>().ok())?;
+            match FieldType::parse(field_name, element, scope)? {
+                element @ (FieldType::Array(_, _) | FieldType::String(_)) => Err((
+                    format!(
+                        "field \"{}\" is an array of {}s",
+                        field_name,
+                        element.name()
+                    ),
+                    String::from("arrays can only hold single values"),
+                )),
+                element => Ok(FieldType::Array(Box::new(element), length)),
+            }
+        } else if let Some(size) = data_type.strip_prefix("string:") {
+            Ok(FieldType::String(parse_string_size(
+                field_name,
+                size.parse::<i64>().ok(),
+            )?))
+        } else if data_type == "string" {
+            Err((
+                format!("string field \"{}\" does not specify its size", field_name),
+                String::from("use string:<size>, eg \"string:32\" for up to 31 characters"),
+            ))
+        } else if data_type == "array" {
+            Err((
+                format!(
+                    "array field \"{}\" does not specify its element type and length",
+                    field_name
+                ),
+                format!(
+                    "use \"array:float:4\" or {} = {{ type = \"array\", of = \"float\", len = 4 }}",
+                    field_name
+                ),
+            ))
+        } else if data_type == "enum" {
+            Err((
+                format!("enum field \"{}\" does not list its values", field_name),
+                format!(
+                    "use {} = {{ type = \"enum\", values = [\"first\", \"second\"] }}",
+                    field_name
+                ),
+            ))
+        } else if data_type == "resource" {
+            Err((
+                format!(
+                    "field \"{}\" is a \"resource\", but does not specify resource type",
+                    field_name
+                ),
+                String::from("use resource:<resource-type>, eg \"resource:mesh\""),
+            ))
+        } else if let Some(struct_type) = scope.types.iter().find(|x| x.name == data_type) {
+            Ok(FieldType::Struct(struct_type.clone()))
+        } else if let Some((namespace, name)) = data_type.split_once('.') {
+            scope.imported_type(field_name, namespace, name)
+        } else {
+            BUILTIN_TYPES.get(data_type).cloned().ok_or_else(|| {
+                (
+                    format!(
+                        "unknown type \"{}\" for field \"{}\"",
+                        data_type, field_name
+                    ),
+                    suggest_type(data_type, scope.types),
+                )
+            })
+        }
+    }
+}
+
+fn parse_array_length(field_name: &str, length: Option<i64>) -> Result<usize, (String, String)> {
+    match length {
+        Some(length) if length > 0 && length as usize <= MAX_ARRAY_LENGTH => Ok(length as usize),
+        _ => Err((
+            format!("array field \"{}\" has an invalid length", field_name),
+            format!(
+                "the length must be a whole number from 1 to {}",
+                MAX_ARRAY_LENGTH
+            ),
+        )),
+    }
+}
+
+fn parse_string_size(field_name: &str, size: Option<i64>) -> Result<usize, (String, String)> {
+    match size {
+        Some(size) if size > 1 && size as usize <= MAX_STRING_SIZE => Ok(size as usize),
+        _ => Err((
+            format!("string field \"{}\" has an invalid size", field_name),
+            format!(
+                "the size counts the NUL terminator and must be a whole number from 2 to {}",
+                MAX_STRING_SIZE
+            ),
+        )),
+    }
+}
+
+fn suggest_type(data_type: &str, user_types: &[Arc<StructType>]) -> String {
+    let mut types: Vec<&str> = BUILTIN_TYPES.keys().copied().collect();
+    types.sort_unstable();
+    types.extend(user_types.iter().map(|x| x.name.as_str()));
+    match types
+        .iter()
+        .map(|name| (strsim::levenshtein(name, data_type), name))
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+    {
+        Some((_, name)) => format!("did you mean \"{}\"?", name),
+        None => format!(
+            "valid types are: {}, enum, string:<size>, array:<type>:<length>, ptr:<type-name> and resource:<resource-type>",
+            types.join(", ")
+        ),
+    }
+}
+
+/// A default value for a field, already checked against the field's type
+#[derive(Clone, PartialEq, Debug)]
+pub enum DefaultValue {
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    /// Values of the components of a vector or color, in memory order
+    Vector(Vec<f64>),
+    Array(Vec<DefaultValue>),
+}
+
+/// What loaders do with values outside of a field's range
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum OutOfRange {
+    /// Fail to load the entity
+    #[default]
+    Reject,
+    /// Move the value to the nearest end of the range, with a warning
+    Clamp,
+}
+
+/// Limits on the values of a numeric or vector field, which apply to each component
+/// of vectors and each element of arrays
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Constraints {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// How much editors change the value by
+    pub step: Option<f64>,
+    /// Vectors are scaled to unit length when loaded
+    pub normalized: bool,
+    pub out_of_range: OutOfRange,
+}
+
+impl Constraints {
+    pub fn has_range(&self) -> bool {
+        self.min.is_some() || self.max.is_some()
+    }
+
+    /// Whether `value` is within the range
+    pub fn contains(&self, value: f64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+
+    /// Describes the range, for error messages, eg "from 0 to 10"
+    pub fn describe_range(&self) -> String {
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => format!("from {} to {}", min, max),
+            (Some(min), None) => format!("of at least {}", min),
+            (None, Some(max)) => format!("of at most {}", max),
+            (None, None) => String::from("of any size"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Field {
+    /// Name as written in TOML files, eg "max-speed"
+    pub name: String,
+    pub data_type: FieldType,
+    pub default: Option<DefaultValue>,
+    /// Optional fields may be left out of entity tables, even without a default
+    pub optional: bool,
+    /// Names of the bits of flags fields, starting from the least significant bit
+    pub bits: Vec<String>,
+    pub description: Option<String>,
+    pub constraints: Constraints,
+}
+
+/// Converts a kebab-case name from a components file to PascalCase
+pub fn pascal_case(name: &str) -> String {
+    CaseStyle::from_kebabcase(name).to_pascalcase()
+}
+
+/// Converts a kebab-case name from a components file to snake_case
+pub fn snake_case(name: &str) -> String {
+    CaseStyle::from_kebabcase(name).to_snakecase()
+}
+
+impl Field {
+    /// Name of the C++ type declared for enum and named flags fields, eg "MovementState"
+    pub fn type_name(&self) -> String {
+        pascal_case(&self.name)
+    }
+
+    /// Required fields must be present in every entity table that uses the component
+    pub fn is_required(&self) -> bool {
+        !self.optional && self.default.is_none()
+    }
+
+    /// Name of the struct member, eg "max_speed"
+    pub fn member_name(&self) -> String {
+        CaseStyle::from_kebabcase(&self.name).to_snakecase()
+    }
+}
+
+/// Engine behavior of a component, set with `_attributes_`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Attribute {
+    /// Has no data, only marks the entities it is attached to
+    Tag,
+    /// At most one entity has the component
+    Singleton,
+    /// Not shown in the editor
+    EditorHidden,
+    /// Not accessible from Lua
+    NoScript,
+}
+
+static ATTRIBUTES: phf::Map<&'static str, Attribute> = phf_map! {
+    "tag" => Attribute::Tag,
+    "singleton" => Attribute::Singleton,
+    "editor-hidden" => Attribute::EditorHidden,
+    "no-script" => Attribute::NoScript,
+};
+
+/// A change to the fields of a component, made in one of its versions, which content
+/// written for an earlier version is migrated with
+#[derive(Clone, Debug)]
+pub struct Migration {
+    /// The `_version_` of the component that made the change
+    pub version: u32,
+    pub rule: MigrationRule,
+}
+
+#[derive(Clone, Debug)]
+pub enum MigrationRule {
+    /// `{ rename = "<field>", to = "<name>" }`
+    Rename { field: String, to: String },
+    /// `{ change-type = "<field>", to = "<type>" }`, converting the values to the new type
+    ChangeType { field: String, to: FieldType },
+    /// `{ split = "<field>", into = [...] }`, replacing a vector by a field for each of
+    /// its components, in order
+    Split { field: String, into: Vec<String> },
+    /// `{ add = "<field>", value = <value> }`, setting the new field where it is missing
+    Add { field: String, value: Value },
+}
+
+/// The rules of a migration, the key each rule needs and an example of it
+const MIGRATION_RULES: [(&str, &str, &str); 4] = [
+    ("rename", "to", "rename = \"velocity\", to = \"speed\""),
+    (
+        "change-type",
+        "to",
+        "change-type = \"count\", to = \"int32\"",
+    ),
+    (
+        "split",
+        "into",
+        "split = \"size\", into = [\"width\", \"height\"]",
+    ),
+    ("add", "value", "add = \"mass\", value = 1.0"),
+];
+
+#[derive(Clone, Debug)]
+pub struct Component {
+    pub name: String,
+    /// The `_namespace_` of the component inside of its file's namespace
+    pub namespace: Option<String>,
+    pub description: Option<String>,
+    pub attributes: Vec<Attribute>,
+    pub fields: Vec<Field>,
+    /// The `_version_` of the component, 1 unless it has been changed in a way that
+    /// content has to be migrated for
+    pub version: u32,
+    /// The changes made in each version, in the order they are applied
+    pub migrations: Vec<Migration>,
+}
+
+impl Component {
+    pub fn has_attribute(&self, attribute: Attribute) -> bool {
+        self.attributes.contains(&attribute)
+    }
+
+    /// Name of the generated struct, eg "RigidBody"
+    pub fn class_name(&self) -> String {
+        pascal_case(&self.name)
+    }
+
+    /// Name of the C++ namespace the component lives in, relative to its file's namespace
+    pub fn namespace_name(&self) -> Option<String> {
+        self.namespace
+            .as_ref()
+            .map(|namespace| CaseStyle::from_kebabcase(namespace).to_snakecase())
+    }
+
+    /// Fully qualified name of the struct in C++ code, eg "components::game::physics::RigidBody"
+    pub fn cpp_name(&self, namespace: &str) -> String {
+        match self.namespace_name() {
+            Some(sub_namespace) => format!(
+                "components::{}::{}::{}",
+                namespace,
+                sub_namespace,
+                self.class_name()
+            ),
+            None => format!("components::{}::{}", namespace, self.class_name()),
+        }
+    }
+
+    /// Name used to refer to the component from entity and scene files, eg "physics/rigid-body"
+    pub fn qualified_name(&self) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}/{}", namespace, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Name of the struct declared for the component in the Lua ffi cdef
+    pub fn lua_struct_name(&self) -> String {
+        format!(
+            "Component_{}_{}",
+            self.namespace_name()
+                .unwrap_or_else(|| String::from("Core")),
+            self.class_name()
+        )
+    }
+}
+
+/// A `[[type]]`, a struct of fields that components can use as the type of a field
+#[derive(Clone, PartialEq, Debug)]
+pub struct StructType {
+    pub name: String,
+    /// The namespace of the file declaring the type
+    pub namespace: String,
+    /// Imported types are declared by the header of another namespace
+    pub imported: bool,
+    pub description: Option<String>,
+    pub fields: Vec<Field>,
+}
+
+impl StructType {
+    /// Name of the generated struct, eg "DamageInfo"
+    pub fn class_name(&self) -> String {
+        pascal_case(&self.name)
+    }
+
+    /// Name of the struct in C++ code, qualified by its namespace if it is imported
+    pub fn cpp_name(&self) -> String {
+        if self.imported {
+            format!("components::{}::{}", self.namespace, self.class_name())
+        } else {
+            self.class_name()
+        }
+    }
+
+    /// Name of the struct declared for the type in the Lua ffi cdef
+    pub fn lua_struct_name(&self) -> String {
+        format!(
+            "Type_{}_{}",
+            pascal_case(&self.namespace),
+            self.class_name()
+        )
+    }
+}
+
+/// A component or `[[type]]` declared by an imported namespace
+#[derive(Clone, PartialEq, Debug)]
+pub struct ImportedName {
+    /// As written in the components file, eg "physics.rigid-body"
+    pub name: String,
+    pub cpp_name: String,
+    pub lua_name: String,
+}
+
+/// The validated contents of a components.toml file
+#[derive(Clone, Debug)]
+pub struct ComponentsFile {
+    pub namespace: String,
+    /// The namespaces named by `imports`, whose headers the generated header includes
+    pub imports: Vec<String>,
+    /// The `[[type]]`s of the file, in the order they were declared
+    pub types: Vec<Arc<StructType>>,
+    pub components: Vec<Component>,
+}
+
+impl ComponentsFile {
+    /// Every field of the types and components of the file
+    pub fn fields(&self) -> impl Iterator<Item = &Field> {
+        self.types
+            .iter()
+            .flat_map(|x| &x.fields)
+            .chain(self.components.iter().flat_map(|x| &x.fields))
+    }
+}
+
+/// What the types of fields can refer to
+struct Scope<'a> {
+    /// The `[[type]]`s declared so far
+    types: &'a [Arc<StructType>],
+    /// The files of the imported namespaces
+    imports: &'a [Arc<ComponentsFile>],
+}
+
+impl Scope<'_> {
+    fn import(
+        &self,
+        field_name: &str,
+        namespace: &str,
+    ) -> Result<&ComponentsFile, (String, String)> {
+        self.imports
+            .iter()
+            .find(|x| x.namespace == namespace)
+            .map(|x| x.as_ref())
+            .ok_or_else(|| {
+                (
+                    format!(
+                        "field \"{}\" refers to namespace \"{}\", which is not imported",
+                        field_name, namespace
+                    ),
+                    format!(
+                        "add \"{}\" to the imports at the top of the file, eg imports = [\"{}\"]",
+                        namespace, namespace
+                    ),
+                )
+            })
+    }
+
+    /// Resolves `<namespace>.<name>`, a `[[type]]` of an imported namespace
+    fn imported_type(
+        &self,
+        field_name: &str,
+        namespace: &str,
+        name: &str,
+    ) -> Result<FieldType, (String, String)> {
+        let import = self.import(field_name, namespace)?;
+        match import.types.iter().find(|x| x.name == name) {
+            Some(struct_type) => Ok(FieldType::Struct(Arc::new(StructType {
+                imported: true,
+                ..StructType::clone(struct_type)
+            }))),
+            None if import.components.iter().any(|x| x.qualified_name() == name) => Err((
+                format!(
+                    "field \"{}\" uses component \"{}.{}\" as its type",
+                    field_name, namespace, name
+                ),
+                format!(
+                    "only [[type]]s can be used as field types, use \"ptr:{}.{}\" to point to the component",
+                    namespace, name
+                ),
+            )),
+            None => Err((
+                format!(
+                    "namespace \"{}\" does not declare a type \"{}\"",
+                    namespace, name
+                ),
+                suggest_name(
+                    name,
+                    import.types.iter().map(|x| x.name.as_str()).collect(),
+                    "types",
+                ),
+            )),
+        }
+    }
+
+    /// Resolves `ptr:<namespace>.<name>`, a pointer to a component or `[[type]]` of an imported namespace
+    fn imported_pointer(
+        &self,
+        field_name: &str,
+        namespace: &str,
+        name: &str,
+    ) -> Result<FieldType, (String, String)> {
+        let import = self.import(field_name, namespace)?;
+        let (cpp_name, lua_name) = match (
+            import.types.iter().find(|x| x.name == name),
+            import
+                .components
+                .iter()
+                .find(|x| x.qualified_name() == name),
+        ) {
+            (Some(struct_type), _) => (
+                format!("components::{}::{}", namespace, struct_type.class_name()),
+                struct_type.lua_struct_name(),
+            ),
+            (None, Some(component)) => (component.cpp_name(namespace), component.lua_struct_name()),
+            (None, None) => {
+                return Err((
+                    format!(
+                        "namespace \"{}\" does not declare a component or type \"{}\"",
+                        namespace, name
+                    ),
+                    suggest_name(
+                        name,
+                        import
+                            .components
+                            .iter()
+                            .map(|x| x.qualified_name())
+                            .chain(import.types.iter().map(|x| x.name.clone()))
+                            .collect::<Vec<String>>()
+                            .iter()
+                            .map(String::as_str)
+                            .collect(),
+                        "names",
+                    ),
+                ))
+            }
+        };
+        Ok(FieldType::ImportedPointer(ImportedName {
+            name: format!("{}.{}", namespace, name),
+            cpp_name,
+            lua_name,
+        }))
+    }
+}
+
+/// Hint suggesting the closest of `names` to `name`, or listing all of them
+pub fn suggest_name(name: &str, mut names: Vec<&str>, what: &str) -> String {
+    names.sort_unstable();
+    match names
+        .iter()
+        .map(|other| (strsim::levenshtein(other, name), other))
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+    {
+        Some((_, other)) => format!("did you mean \"{}\"?", other),
+        None if names.is_empty() => format!("the namespace does not declare any {}", what),
+        None => format!("valid {} are: {}", what, names.join(", ")),
+    }
+}
+
+fn is_reserved(key: &str) -> bool {
+    key.starts_with('_') && key.ends_with('_')
+}
+
+fn parse_string(
+    diagnostics: &mut Diagnostics,
+    table: Option<Location>,
+    item: Option<&Item>,
+    key: &str,
+    context: &str,
+) -> Option<String> {
+    let item = item?;
+    match item.as_str() {
+        Some(string) => Some(string.to_string()),
+        None => {
+            let location = diagnostics
+                .locator
+                .key(table, key)
+                .map(|k| diagnostics.locator.value(k));
+            diagnostics.error(
+                location.or(table),
+                format!("{} \"{}\" must be a string", context, key),
+                None,
+            );
+            None
+        }
+    }
+}
+
+fn parse_field_type(
+    diagnostics: &mut Diagnostics,
+    scope: &Scope,
+    location: Option<Location>,
+    field_name: &str,
+    data_type: &str,
+) -> Option<FieldType> {
+    match FieldType::parse(field_name, data_type, scope) {
+        Ok(data_type) => Some(data_type),
+        Err((message, hint)) => {
+            diagnostics.error(location, message, Some(hint));
+            None
+        }
+    }
+}
+
+fn parse_field(
+    diagnostics: &mut Diagnostics,
+    scope: &Scope,
+    table: Option<Location>,
+    field_name: &str,
+    field_type: &Item,
+) -> Option<Field> {
+    let key = diagnostics.locator.key(table, field_name);
+    if let Some(data_type) = field_type.as_str() {
+        let location = key.map(|k| diagnostics.locator.value(k)).or(table);
+        Some(Field {
+            name: field_name.to_string(),
+            data_type: parse_field_type(diagnostics, scope, location, field_name, data_type)?,
+            default: None,
+            optional: false,
+            bits: Vec::new(),
+            description: None,
+            constraints: Constraints::default(),
+        })
+    } else if let Some(field) = field_type.as_table_like() {
+        parse_field_table(diagnostics, scope, table, key, field_name, field)
+    } else {
+        diagnostics.error(
+            key.or(table),
+            format!(
+                "\"{}\" must be a string or a table naming the field type",
+                field_name
+            ),
+            Some(format!(
+                "eg {0} = \"float\" or {0} = {{ type = \"float\" }}",
+                field_name
+            )),
+        );
+        None
+    }
+}
+
+/// Keys that may be used in a field table, eg `mass = { type = "float", default = 1.0 }`
+const FIELD_KEYS: [&str; 13] = [
+    "type",
+    "default",
+    "optional",
+    "values",
+    "bits",
+    "of",
+    "len",
+    "description",
+    "min",
+    "max",
+    "step",
+    "normalized",
+    "out-of-range",
+];
+
+const CONSTRAINT_KEYS: [&str; 5] = ["min", "max", "step", "normalized", "out-of-range"];
+
+/// Location of the value of `name` inside of a field table, falling back to the field itself
+fn field_key_location(
+    diagnostics: &Diagnostics,
+    table: Option<Location>,
+    key: Option<Location>,
+    name: &str,
+) -> Option<Location> {
+    key.and_then(|k| diagnostics.locator.inline_key(k, name))
+        .map(|k| diagnostics.locator.value(k))
+        .or(key)
+        .or(table)
+}
+
+fn parse_field_table(
+    diagnostics: &mut Diagnostics,
+    scope: &Scope,
+    table: Option<Location>,
+    key: Option<Location>,
+    field_name: &str,
+    field: &dyn TableLike,
+) -> Option<Field> {
+    for (name, _) in field.iter() {
+        if !FIELD_KEYS.contains(&name) {
+            let location = key
+                .and_then(|k| diagnostics.locator.inline_key(k, name))
+                .or(key)
+                .or(table);
+            diagnostics.error(
+                location,
+                format!("unknown key \"{}\" in field \"{}\"", name, field_name),
+                Some(format!("valid keys are: {}", FIELD_KEYS.join(", "))),
+            );
+        }
+    }
+
+    let location = field_key_location(diagnostics, table, key, "type");
+    let data_type = match field.get("type").and_then(Item::as_str) {
+        Some("array") if field.contains_key("of") || field.contains_key("len") => {
+            parse_array_table(diagnostics, scope, table, key, field_name, field)
+        }
+        Some(_) => {
+            for name in ["of", "len"] {
+                if field.contains_key(name) {
+                    let location = field_key_location(diagnostics, table, key, name);
+                    diagnostics.error(
+                        location,
+                        format!(
+                            "field \"{}\" has \"{}\" but is not an array",
+                            field_name, name
+                        ),
+                        Some(format!("{} can only be used with type = \"array\"", name)),
+                    );
+                }
+            }
+            parse_table_type(diagnostics, scope, table, key, field_name, field, "type")
+        }
+        None => {
+            diagnostics.error(
+                location,
+                format!("field \"{}\" must have a string \"type\"", field_name),
+                Some(format!("eg {} = {{ type = \"float\" }}", field_name)),
+            );
+            None
+        }
+    };
+
+    let optional = match field.get("optional") {
+        Some(optional) => optional.as_bool().or_else(|| {
+            let location = field_key_location(diagnostics, table, key, "optional");
+            diagnostics.error(
+                location,
+                format!(
+                    "\"optional\" of field \"{}\" must be true or false",
+                    field_name
+                ),
+                None,
+            );
+            None
+        }),
+        None => Some(false),
+    };
+
+    let description = match field.get("description") {
+        Some(description) => match description.as_str() {
+            Some(description) => Some(Some(description.to_string())),
+            None => {
+                let location = field_key_location(diagnostics, table, key, "description");
+                diagnostics.error(
+                    location,
+                    format!(
+                        "\"description\" of field \"{}\" must be a string",
+                        field_name
+                    ),
+                    None,
+                );
+                None
+            }
+        },
+        None => Some(None),
+    };
+
+    let bits = match (field.get("bits"), &data_type) {
+        (Some(bits), Some(data_type)) => {
+            let location = field_key_location(diagnostics, table, key, "bits");
+            parse_bits(diagnostics, location, field_name, data_type, bits)
+        }
+        _ => Some(Vec::new()),
+    };
+
+    let default = match (field.get("default").and_then(Item::as_value), &data_type) {
+        (Some(value), Some(data_type)) => {
+            let location = field_key_location(diagnostics, table, key, "default");
+            let bits = bits.as_deref().unwrap_or_default();
+            parse_default(diagnostics, location, field_name, data_type, bits, value).map(Some)
+        }
+        _ => Some(None),
+    };
+
+    let constraints = match &data_type {
+        Some(data_type) => parse_constraints(diagnostics, table, key, field_name, data_type, field),
+        None => Some(Constraints::default()),
+    };
+
+    if let (Some(Some(default)), Some(constraints)) = (&default, &constraints) {
+        if !default_numbers(default)
+            .into_iter()
+            .all(|number| constraints.contains(number))
+        {
+            let location = field_key_location(diagnostics, table, key, "default");
+            diagnostics.error(
+                location,
+                format!(
+                    "default value of field \"{}\" is outside of its range",
+                    field_name
+                ),
+                Some(format!(
+                    "the range allows values {}",
+                    constraints.describe_range()
+                )),
+            );
+            return None;
+        }
+    }
+
+    Some(Field {
+        name: field_name.to_string(),
+        data_type: data_type?,
+        default: default?,
+        optional: optional?,
+        bits: bits?,
+        description: description?,
+        constraints: constraints?,
+    })
+}
+
+/// The numbers of a default value that a field's range applies to
+fn default_numbers(default: &DefaultValue) -> Vec<f64> {
+    match default {
+        DefaultValue::Integer(value) => vec![*value as f64],
+        DefaultValue::Float(value) => vec![*value],
+        DefaultValue::Vector(values) => values.clone(),
+        DefaultValue::Array(values) => values.iter().flat_map(default_numbers).collect(),
+        DefaultValue::Bool(_) | DefaultValue::String(_) => Vec::new(),
+    }
+}
+
+/// Parses a `min`, `max` or `step` number, which must be a whole number that fits in
+/// integer fields
+fn parse_limit(
+    diagnostics: &mut Diagnostics,
+    location: Option<Location>,
+    field_name: &str,
+    data_type: &FieldType,
+    name: &str,
+    value: &Item,
+) -> Option<f64> {
+    let number = match data_type.integer_range() {
+        Some((min, max)) => value
+            .as_integer()
+            .filter(|integer| (*integer as i128) >= min && (*integer as i128) <= max)
+            .map(|integer| integer as f64),
+        None => value
+            .as_float()
+            .or_else(|| value.as_integer().map(|i| i as f64)),
+    };
+    if number.is_none() {
+        diagnostics.error(
+            location,
+            format!(
+                "\"{}\" of field \"{}\" is not a valid \"{}\"",
+                name,
+                field_name,
+                data_type.name()
+            ),
+            Some(match data_type.integer_range() {
+                Some((min, max)) => format!("use a whole number between {} and {}", min, max),
+                None => format!("use a number, eg {} = 1.0", name),
+            }),
+        );
+    }
+    number
+}
+
+fn parse_constraints(
+    diagnostics: &mut Diagnostics,
+    table: Option<Location>,
+    key: Option<Location>,
+    field_name: &str,
+    data_type: &FieldType,
+    field: &dyn TableLike,
+) -> Option<Constraints> {
+    // Arrays are limited element by element
+    let data_type = data_type.element_type();
+    let mut constraints = Constraints::default();
+    if !data_type.is_numeric() {
+        let mut valid = true;
+        for name in CONSTRAINT_KEYS
+            .iter()
+            .filter(|name| field.contains_key(name))
+        {
+            let location = field_key_location(diagnostics, table, key, name);
+            diagnostics.error(
+                location,
+                format!(
+                    "field \"{}\" has \"{}\" but is not a number or a vector",
+                    field_name, name
+                ),
+                Some(format!(
+                    "{} can only be used with integer, float, double, vector and color types",
+                    name
+                )),
+            );
+            valid = false;
+        }
+        return valid.then_some(constraints);
+    }
+
+    let mut valid = true;
+    for name in ["min", "max", "step"] {
+        if let Some(value) = field.get(name) {
+            let location = field_key_location(diagnostics, table, key, name);
+            let limit = parse_limit(diagnostics, location, field_name, data_type, name, value);
+            valid &= limit.is_some();
+            match name {
+                "min" => constraints.min = limit,
+                "max" => constraints.max = limit,
+                _ => constraints.step = limit,
+            }
+        }
+    }
+    if let (Some(min), Some(max)) = (constraints.min, constraints.max) {
+        if min > max {
+            let location = field_key_location(diagnostics, table, key, "max");
+            diagnostics.error(
+                location,
+                format!(
+                    "\"max\" of field \"{}\" is smaller than its \"min\"",
+                    field_name
+                ),
+                None,
+            );
+            valid = false;
+        }
+    }
+    if let Some(step) = constraints.step {
+        if step <= 0.0 {
+            let location = field_key_location(diagnostics, table, key, "step");
+            diagnostics.error(
+                location,
+                format!("\"step\" of field \"{}\" must be positive", field_name),
+                None,
+            );
+            valid = false;
+        }
+    }
+
+    if let Some(normalized) = field.get("normalized") {
+        let location = field_key_location(diagnostics, table, key, "normalized");
+        match normalized.as_bool() {
+            Some(normalized)
+                if !normalized
+                    || matches!(
+                        data_type,
+                        FieldType::Vec2 | FieldType::Vec3 | FieldType::Vec4
+                    ) =>
+            {
+                constraints.normalized = normalized
+            }
+            Some(_) => {
+                diagnostics.error(
+                    location,
+                    format!("field \"{}\" is normalized but is not a vector", field_name),
+                    Some(String::from(
+                        "normalized can only be used with vec2, vec3 and vec4",
+                    )),
+                );
+                valid = false;
+            }
+            None => {
+                diagnostics.error(
+                    location,
+                    format!(
+                        "\"normalized\" of field \"{}\" must be true or false",
+                        field_name
+                    ),
+                    None,
+                );
+                valid = false;
+            }
+        }
+    }
+
+    if let Some(out_of_range) = field.get("out-of-range") {
+        let location = field_key_location(diagnostics, table, key, "out-of-range");
+        match out_of_range.as_str() {
+            _ if !constraints.has_range() => {
+                diagnostics.error(
+                    location,
+                    format!(
+                        "field \"{}\" has \"out-of-range\" but no \"min\" or \"max\"",
+                        field_name
+                    ),
+                    None,
+                );
+                valid = false;
+            }
+            Some("reject") => constraints.out_of_range = OutOfRange::Reject,
+            Some("clamp") => constraints.out_of_range = OutOfRange::Clamp,
+            _ => {
+                diagnostics.error(
+                    location,
+                    format!(
+                        "\"out-of-range\" of field \"{}\" must be \"reject\" or \"clamp\"",
+                        field_name
+                    ),
+                    Some(String::from(
+                        "reject fails to load values outside of the range, clamp moves them into it",
+                    )),
+                );
+                valid = false;
+            }
+        }
+    }
+    valid.then_some(constraints)
+}
+
+/// Resolves the type named by the string at `type_key` in a field table, which may be an enum
+fn parse_table_type(
+    diagnostics: &mut Diagnostics,
+    scope: &Scope,
+    table: Option<Location>,
+    key: Option<Location>,
+    field_name: &str,
+    field: &dyn TableLike,
+    type_key: &str,
+) -> Option<FieldType> {
+    let location = field_key_location(diagnostics, table, key, type_key);
+    let data_type = field
+        .get(type_key)
+        .and_then(Item::as_str)
+        .unwrap_or_default();
+    match field.get("values") {
+        Some(values) if data_type == "enum" => {
+            let location = field_key_location(diagnostics, table, key, "values");
+            parse_enum_values(diagnostics, location, field_name, values).map(FieldType::Enum)
+        }
+        Some(_) => {
+            let location = field_key_location(diagnostics, table, key, "values");
+            diagnostics.error(
+                location,
+                format!("field \"{}\" has \"values\" but is not an enum", field_name),
+                Some(String::from("values can only be used with type = \"enum\"")),
+            );
+            None
+        }
+        None => parse_field_type(diagnostics, scope, location, field_name, data_type),
+    }
+}
+
+fn parse_array_table(
+    diagnostics: &mut Diagnostics,
+    scope: &Scope,
+    table: Option<Location>,
+    key: Option<Location>,
+    field_name: &str,
+    field: &dyn TableLike,
+) -> Option<FieldType> {
+    let location = field_key_location(diagnostics, table, key, "of");
+    let element = match field.get("of").and_then(Item::as_str) {
+        Some(_) => {
+            match parse_table_type(diagnostics, scope, table, key, field_name, field, "of") {
+                Some(element @ (FieldType::Array(_, _) | FieldType::String(_))) => {
+                    diagnostics.error(
+                        location,
+                        format!(
+                            "field \"{}\" is an array of {}s",
+                            field_name,
+                            element.name()
+                        ),
+                        Some(String::from("arrays can only hold single values")),
+                    );
+                    None
+                }
+                element => element,
+            }
+        }
+        None => {
+            diagnostics.error(
+                location,
+                format!(
+                    "array field \"{}\" must name its element type with \"of\"",
+                    field_name
+                ),
+                Some(format!(
+                    "eg {} = {{ type = \"array\", of = \"float\", len = 4 }}",
+                    field_name
+                )),
+            );
+            None
+        }
+    };
+    let location = field_key_location(diagnostics, table, key, "len");
+    let length = match parse_array_length(field_name, field.get("len").and_then(Item::as_integer)) {
+        Ok(length) => Some(length),
+        Err((message, hint)) => {
+            diagnostics.error(location, message, Some(hint));
+            None
+        }
+    };
+    Some(FieldType::Array(Box::new(element?), length?))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Parses a list of names, eg the values of an enum or the bits of a flags field
+fn parse_names(
+    diagnostics: &mut Diagnostics,
+    location: Option<Location>,
+    field_name: &str,
+    key: &str,
+    names: &Item,
+) -> Option<Vec<String>> {
+    let names = match names.as_array() {
+        Some(array) if !array.is_empty() => array
+            .iter()
+            .map(|name| name.as_str().map(String::from))
+            .collect::<Option<Vec<String>>>(),
+        _ => None,
+    };
+    let names = match names {
+        Some(names) => names,
+        None => {
+            diagnostics.error(
+                location,
+                format!(
+                    "\"{}\" of field \"{}\" must be a list of names",
+                    key, field_name
+                ),
+                Some(format!("eg {} = [\"first\", \"second\", \"third\"]", key)),
+            );
+            return None;
+        }
+    };
+    let mut valid = true;
+    for (index, name) in names.iter().enumerate() {
+        if !is_identifier(name) {
+            diagnostics.error(
+                location,
+                format!(
+                    "\"{}\" is not a valid name in \"{}\" of field \"{}\"",
+                    name, key, field_name
+                ),
+                Some(String::from(
+                    "names must start with a letter and contain only letters, digits, - and _",
+                )),
+            );
+            valid = false;
+        } else if names[..index]
+            .iter()
+            .any(|other| pascal_case(other) == pascal_case(name))
+        {
+            diagnostics.error(
+                location,
+                format!(
+                    "\"{}\" is listed twice in \"{}\" of field \"{}\"",
+                    name, key, field_name
+                ),
+                None,
+            );
+            valid = false;
+        }
+    }
+    if valid {
+        Some(names)
+    } else {
+        None
+    }
+}
+
+fn parse_enum_values(
+    diagnostics: &mut Diagnostics,
+    location: Option<Location>,
+    field_name: &str,
+    values: &Item,
+) -> Option<Vec<String>> {
+    let values = parse_names(diagnostics, location, field_name, "values", values)?;
+    if values.len() > u16::MAX as usize + 1 {
+        diagnostics.error(
+            location,
+            format!("enum field \"{}\" has too many values", field_name),
+            Some(format!(
+                "at most {} values are supported",
+                u16::MAX as usize + 1
+            )),
+        );
+        return None;
+    }
+    Some(values)
+}
+
+fn parse_bits(
+    diagnostics: &mut Diagnostics,
+    location: Option<Location>,
+    field_name: &str,
+    data_type: &FieldType,
+    bits: &Item,
+) -> Option<Vec<String>> {
+    let width = match data_type.element_type().flags_width() {
+        Some(width) => width,
+        None => {
+            diagnostics.error(
+                location,
+                format!(
+                    "field \"{}\" has \"bits\" but is not a flags field",
+                    field_name
+                ),
+                Some(String::from(
+                    "bits can only be used with flags8, flags16, flags32 or flags64",
+                )),
+            );
+            return None;
+        }
+    };
+    let bits = parse_names(diagnostics, location, field_name, "bits", bits)?;
+    if bits.len() > width {
+        diagnostics.error(
+            location,
+            format!(
+                "field \"{}\" names {} bits, but a \"{}\" only has {}",
+                field_name,
+                bits.len(),
+                data_type.element_type().name(),
+                width
+            ),
+            Some(String::from("use a wider flags type or fewer bits")),
+        );
+        return None;
+    }
+    Some(bits)
+}
+
+fn parse_vector_default(components: &[&str], value: &Value) -> Option<Vec<f64>> {
+    let number = |value: &Value| {
+        value
+            .as_float()
+            .or_else(|| value.as_integer().map(|i| i as f64))
+    };
+    match value {
+        Value::Array(array) if array.len() == components.len() => {
+            array.iter().map(number).collect()
+        }
+        Value::InlineTable(table) if table.len() == components.len() => components
+            .iter()
+            .map(|c| table.get(c).and_then(number))
+            .collect(),
+        _ => None,
+    }
+}
+
+fn parse_default(
+    diagnostics: &mut Diagnostics,
+    location: Option<Location>,
+    field_name: &str,
+    data_type: &FieldType,
+    bits: &[String],
+    value: &Value,
+) -> Option<DefaultValue> {
+    if let FieldType::Array(element, length) = data_type {
+        if let FieldType::Resource(_) = **element {
+            diagnostics.error(
+                location,
+                format!(
+                    "field \"{}\" is an array of resources, which cannot have a default value",
+                    field_name
+                ),
+                None,
+            );
+            return None;
+        }
+        return match value.as_array() {
+            Some(array) if array.len() == *length => array
+                .iter()
+                .map(|value| parse_default(diagnostics, location, field_name, element, bits, value))
+                .collect::<Option<Vec<DefaultValue>>>()
+                .map(DefaultValue::Array),
+            _ => {
+                diagnostics.error(
+                    location,
+                    format!(
+                        "default value of field \"{}\" must be an array of {} values",
+                        field_name, length
+                    ),
+                    None,
+                );
+                None
+            }
+        };
+    }
+    if let (false, Some(array)) = (bits.is_empty(), value.as_array()) {
+        let mut mask: u64 = 0;
+        for bit in array.iter() {
+            match bit.as_str().and_then(|b| bits.iter().position(|x| x == b)) {
+                Some(position) => mask |= 1 << position,
+                None => {
+                    diagnostics.error(
+                        location,
+                        format!(
+                            "default value of field \"{}\" must only list its bits",
+                            field_name
+                        ),
+                        Some(format!("use some of: {}", bits.join(", "))),
+                    );
+                    return None;
+                }
+            }
+        }
+        // Stored as the same bit pattern, flags64 defaults may not fit in an i64
+        return Some(DefaultValue::Integer(mask as i64));
+    }
+    let default = match data_type {
+        FieldType::Float | FieldType::Double => value
+            .as_float()
+            .or_else(|| value.as_integer().map(|i| i as f64))
+            .map(DefaultValue::Float),
+        FieldType::Bool => value.as_bool().map(DefaultValue::Bool),
+        FieldType::HashedString | FieldType::Ref | FieldType::Signal | FieldType::Resource(_) => {
+            value.as_str().map(|s| DefaultValue::String(s.to_string()))
+        }
+        FieldType::Enum(values) => value
+            .as_str()
+            .filter(|s| values.iter().any(|v| v == s))
+            .map(|s| DefaultValue::String(s.to_string())),
+        FieldType::String(size) => match value.as_str() {
+            Some(text) if text.len() >= *size => {
+                diagnostics.error(
+                    location,
+                    format!(
+                        "default value of field \"{}\" does not fit in a \"string:{}\"",
+                        field_name, size
+                    ),
+                    Some(format!(
+                        "use at most {} bytes, the last byte holds the NUL terminator",
+                        size - 1
+                    )),
+                );
+                return None;
+            }
+            text => text.map(|s| DefaultValue::String(s.to_string())),
+        },
+        FieldType::Struct(struct_type) => {
+            diagnostics.error(
+                location,
+                format!(
+                    "field \"{}\" of type \"{}\" cannot have a default value",
+                    field_name, struct_type.name
+                ),
+                Some(format!(
+                    "give the fields of [[type]] \"{}\" default values instead",
+                    struct_type.name
+                )),
+            );
+            return None;
+        }
+        FieldType::Entity | FieldType::Pointer(_) | FieldType::ImportedPointer(_) => {
+            diagnostics.error(
+                location,
+                format!(
+                    "field \"{}\" of type \"{}\" cannot have a default value",
+                    field_name,
+                    data_type.name()
+                ),
+                None,
+            );
+            return None;
+        }
+        _ => match data_type.vector_components() {
+            Some(components) => parse_vector_default(components, value).map(DefaultValue::Vector),
+            None => value.as_integer().map(DefaultValue::Integer),
+        },
+    };
+    match (&default, data_type.integer_range()) {
+        (Some(DefaultValue::Integer(integer)), Some((min, max)))
+            if (*integer as i128) < min || (*integer as i128) > max =>
+        {
+            diagnostics.error(
+                location,
+                format!(
+                    "default value of field \"{}\" does not fit in a \"{}\"",
+                    field_name,
+                    data_type.name()
+                ),
+                Some(format!("must be between {} and {}", min, max)),
+            );
+            None
+        }
+        (Some(_), _) => default,
+        (None, _) => {
+            diagnostics.error(
+                location,
+                format!(
+                    "default value of field \"{}\" is not a valid \"{}\"",
+                    field_name,
+                    data_type.name()
+                ),
+                Some(default_hint(data_type)),
+            );
+            None
+        }
+    }
+}
+
+fn default_hint(data_type: &FieldType) -> String {
+    match data_type {
+        FieldType::Enum(values) => format!("use one of: {}", values.join(", ")),
+        FieldType::Float | FieldType::Double => String::from("use a number, eg default = 1.0"),
+        FieldType::Bool => String::from("use true or false"),
+        FieldType::HashedString
+        | FieldType::Ref
+        | FieldType::Signal
+        | FieldType::Resource(_)
+        | FieldType::String(_) => String::from("use a string, eg default = \"name\""),
+        _ if data_type.flags_width().is_some() => {
+            String::from("use a whole number, or a list of bit names if the field has bits")
+        }
+        _ => match data_type.vector_components() {
+            Some(components) => format!(
+                "use {{ {} }} or an array of {} numbers",
+                components
+                    .iter()
+                    .map(|c| format!("{} = 0.0", c))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                components.len()
+            ),
+            None => String::from("use a whole number, eg default = 0"),
+        },
+    }
+}
+
+fn parse_attributes(
+    diagnostics: &mut Diagnostics,
+    table: Option<Location>,
+    attributes: &Item,
+) -> Option<Vec<Attribute>> {
+    let location = diagnostics
+        .locator
+        .key(table, "_attributes_")
+        .map(|k| diagnostics.locator.value(k))
+        .or(table);
+    let mut names: Vec<&str> = ATTRIBUTES.keys().copied().collect();
+    names.sort_unstable();
+    let list = match attributes.as_array() {
+        Some(array) => array
+            .iter()
+            .map(Value::as_str)
+            .collect::<Option<Vec<&str>>>(),
+        None => None,
+    };
+    let list = match list {
+        Some(list) => list,
+        None => {
+            diagnostics.error(
+                location,
+                String::from("\"_attributes_\" of component must be a list of names"),
+                Some(String::from(
+                    "eg _attributes_ = [\"singleton\", \"no-script\"]",
+                )),
+            );
+            return None;
+        }
+    };
+    let mut attributes = Vec::new();
+    let mut valid = true;
+    for name in list {
+        match ATTRIBUTES.get(name) {
+            Some(attribute) if attributes.contains(attribute) => {
+                diagnostics.error(
+                    location,
+                    format!("attribute \"{}\" is listed twice", name),
+                    None,
+                );
+                valid = false;
+            }
+            Some(attribute) => attributes.push(*attribute),
+            None => {
+                diagnostics.error(
+                    location,
+                    format!("unknown component attribute \"{}\"", name),
+                    Some(
+                        match names
+                            .iter()
+                            .find(|other| strsim::levenshtein(other, name) <= 2)
+                        {
+                            Some(other) => format!("did you mean \"{}\"?", other),
+                            None => format!("valid attributes are: {}", names.join(", ")),
+                        },
+                    ),
+                );
+                valid = false;
+            }
+        }
+    }
+    if valid {
+        Some(attributes)
+    } else {
+        None
+    }
+}
+
+fn parse_version(
+    diagnostics: &mut Diagnostics,
+    table: Option<Location>,
+    version: &Item,
+) -> Option<u32> {
+    match version.as_integer() {
+        Some(version) if version >= 1 && version <= u32::MAX as i64 => Some(version as u32),
+        _ => {
+            let location = diagnostics
+                .locator
+                .key(table, "_version_")
+                .map(|k| diagnostics.locator.value(k))
+                .or(table);
+            diagnostics.error(
+                location,
+                String::from("\"_version_\" of component must be a whole number from 1"),
+                Some(String::from("eg _version_ = 2")),
+            );
+            None
+        }
+    }
+}
+
+/// Parses one of the `_migrations_` of a component at `version`. `location` is where
+/// errors are reported, as entries of the list cannot be located by themselves.
+fn parse_migration(
+    diagnostics: &mut Diagnostics,
+    scope: &Scope,
+    location: Option<Location>,
+    version: u32,
+    migration: &dyn TableLike,
+) -> Option<Migration> {
+    let mut valid = true;
+    for (key, _) in migration.iter() {
+        if key != "version"
+            && !MIGRATION_RULES
+                .iter()
+                .any(|(rule, argument, _)| key == *rule || key == *argument)
+        {
+            diagnostics.error(
+                location,
+                format!("unknown key \"{}\" in migration", key),
+                Some(String::from(
+                    "valid keys are: version, rename, change-type, split, add, to, into, value",
+                )),
+            );
+            valid = false;
+        }
+    }
+
+    let rules: Vec<&(&str, &str, &str)> = MIGRATION_RULES
+        .iter()
+        .filter(|(rule, _, _)| migration.contains_key(rule))
+        .collect();
+    let (rule, argument, example) = match rules[..] {
+        [rule] => *rule,
+        _ => {
+            diagnostics.error(
+                location,
+                String::from("migration must have exactly one of: rename, change-type, split, add"),
+                Some(String::from(
+                    "eg { version = 2, rename = \"velocity\", to = \"speed\" }",
+                )),
+            );
+            return None;
+        }
+    };
+    let hint = || Some(format!("eg {{ version = 2, {} }}", example));
+    for (_, other, _) in MIGRATION_RULES {
+        if other != argument && migration.contains_key(other) {
+            diagnostics.error(
+                location,
+                format!("\"{}\" cannot be used in a {} migration", other, rule),
+                hint(),
+            );
+            valid = false;
+        }
+    }
+
+    let target = match migration.get("version").and_then(Item::as_integer) {
+        Some(target) if target >= 2 && target <= version as i64 => Some(target as u32),
+        Some(target) => {
+            diagnostics.error(
+                location,
+                format!(
+                    "{} migration is for version {}, but the component is at version {}",
+                    rule, target, version
+                ),
+                Some(String::from(
+                    "migrations are for the versions from 2 to the component's _version_, \
+                     set _version_ to the version that made the change",
+                )),
+            );
+            None
+        }
+        None => {
+            diagnostics.error(
+                location,
+                format!("{} migration must have a whole number \"version\"", rule),
+                Some(String::from(
+                    "the version of the component that made the change, eg version = 2",
+                )),
+            );
+            None
+        }
+    };
+    let field = match migration.get(rule).and_then(Item::as_str) {
+        Some(field) => field.to_string(),
+        None => {
+            diagnostics.error(
+                location,
+                format!("\"{}\" of migration must be a field name", rule),
+                hint(),
+            );
+            return None;
+        }
+    };
+    let value = match migration.get(argument) {
+        Some(value) => value,
+        None => {
+            diagnostics.error(
+                location,
+                format!(
+                    "{} migration of \"{}\" is missing \"{}\"",
+                    rule, field, argument
+                ),
+                hint(),
+            );
+            return None;
+        }
+    };
+    let string = |diagnostics: &mut Diagnostics| {
+        let string = value.as_str().map(str::to_string);
+        if string.is_none() {
+            diagnostics.error(
+                location,
+                format!("\"{}\" of {} migration must be a string", argument, rule),
+                hint(),
+            );
+        }
+        string
+    };
+
+    let rule = match rule {
+        "rename" => MigrationRule::Rename {
+            to: string(diagnostics)?,
+            field,
+        },
+        "change-type" => {
+            let to = string(diagnostics)?;
+            let to = parse_field_type(diagnostics, scope, location, &field, &to)?;
+            if !to.is_scalar() {
+                diagnostics.error(
+                    location,
+                    format!("cannot change the type of \"{}\" to {}", field, to.name()),
+                    Some(String::from(
+                        "values can only be changed to number, bool and string types",
+                    )),
+                );
+                return None;
+            }
+            MigrationRule::ChangeType { field, to }
+        }
+        "split" => {
+            let into = value
+                .as_array()
+                .map(|array| {
+                    array
+                        .iter()
+                        .map(|name| name.as_str().map(str::to_string))
+                        .collect::<Option<Vec<String>>>()
+                })
+                .unwrap_or_default()
+                .filter(|into| (2..=4).contains(&into.len()));
+            match into {
+                Some(into) => MigrationRule::Split { field, into },
+                None => {
+                    diagnostics.error(
+                        location,
+                        format!(
+                            "\"into\" of split migration of \"{}\" must list a field name \
+                             for each component of the vector",
+                            field
+                        ),
+                        hint(),
+                    );
+                    return None;
+                }
+            }
+        }
+        _ => match value.as_value() {
+            Some(value) => MigrationRule::Add {
+                field,
+                value: value.clone(),
+            },
+            None => {
+                diagnostics.error(
+                    location,
+                    format!(
+                        "\"value\" of add migration of \"{}\" must be a value",
+                        field
+                    ),
+                    hint(),
+                );
+                return None;
+            }
+        },
+    };
+    if !valid {
+        return None;
+    }
+    Some(Migration {
+        version: target?,
+        rule,
+    })
+}
+
+/// Parses the `_migrations_` of a component, a list of tables, ordering them by version
+fn parse_migrations(
+    diagnostics: &mut Diagnostics,
+    scope: &Scope,
+    table: Option<Location>,
+    version: u32,
+    migrations: &Item,
+) -> Option<Vec<Migration>> {
+    let location = diagnostics
+        .locator
+        .key(table, "_migrations_")
+        .map(|k| diagnostics.locator.value(k))
+        .or(table);
+    let tables: Option<Vec<&dyn TableLike>> = match migrations {
+        Item::ArrayOfTables(tables) => Some(tables.iter().map(|x| x as &dyn TableLike).collect()),
+        _ => migrations.as_array().and_then(|array| {
+            array
+                .iter()
+                .map(|x| x.as_inline_table().map(|x| x as &dyn TableLike))
+                .collect()
+        }),
+    };
+    let tables = match tables {
+        Some(tables) => tables,
+        None => {
+            diagnostics.error(
+                location,
+                String::from("\"_migrations_\" of component must be a list of tables"),
+                Some(String::from(
+                    "eg _migrations_ = [{ version = 2, rename = \"velocity\", to = \"speed\" }]",
+                )),
+            );
+            return None;
+        }
+    };
+    let mut migrations = tables
+        .into_iter()
+        .map(|migration| parse_migration(diagnostics, scope, location, version, migration))
+        .collect::<Vec<Option<Migration>>>()
+        .into_iter()
+        .collect::<Option<Vec<Migration>>>()?;
+    // Stable, so that the changes of a version are applied in the order they are listed
+    migrations.sort_by_key(|migration| migration.version);
+    Some(migrations)
+}
+
+/// Parses a `[[component]]`. `previous` are the components declared above it, which it
+/// must not share a struct with.
+fn parse_component(
+    diagnostics: &mut Diagnostics,
+    scope: &Scope,
+    index: usize,
+    component: &Table,
+    previous: &[Option<Component>],
+) -> Option<Component> {
+    let table = Some(diagnostics.locator.array_table("component", index));
+    let location = |diagnostics: &Diagnostics, key: &str| {
+        diagnostics
+            .locator
+            .key(table, key)
+            .map(|k| diagnostics.locator.value(k))
+            .or(table)
+    };
+    let name = match component.get("_name_") {
+        Some(name) => parse_string(diagnostics, table, Some(name), "_name_", "component"),
+        None => {
+            diagnostics.error(
+                table,
+                String::from("component is missing its \"_name_\""),
+                Some(String::from(
+                    "every [[component]] needs a name, eg _name_ = \"position\"",
+                )),
+            );
+            None
+        }
+    };
+    let name = match name {
+        Some(name) if !is_identifier(&name) => {
+            diagnostics.error(
+                location(diagnostics, "_name_"),
+                format!("component name \"{}\" is not a valid name", name),
+                Some(String::from(
+                    "use letters, digits and dashes, starting with a letter",
+                )),
+            );
+            None
+        }
+        name => name,
+    };
+    let namespace = match parse_string(
+        diagnostics,
+        table,
+        component.get("_namespace_"),
+        "_namespace_",
+        "component",
+    ) {
+        Some(namespace) if !is_identifier(&namespace) => {
+            diagnostics.error(
+                location(diagnostics, "_namespace_"),
+                format!("component namespace \"{}\" is not a valid name", namespace),
+                Some(String::from(
+                    "use letters, digits and dashes, starting with a letter",
+                )),
+            );
+            None
+        }
+        namespace => namespace,
+    };
+    // Names that only differ in dashes and underscores would declare the same struct,
+    // or the same functions
+    let clash = name.as_ref().and_then(|name| {
+        previous.iter().flatten().find(|other| {
+            other.namespace == namespace
+                && (other.class_name() == pascal_case(name)
+                    || snake_case(&other.name) == snake_case(name))
+        })
+    });
+    let name = match (name, clash) {
+        (Some(name), Some(other)) if other.name == name => {
+            diagnostics.error(
+                location(diagnostics, "_name_"),
+                format!("component \"{}\" is declared more than once", name),
+                None,
+            );
+            None
+        }
+        (Some(name), Some(other)) => {
+            diagnostics.error(
+                location(diagnostics, "_name_"),
+                format!(
+                    "component \"{}\" would generate the same names as \"{}\"",
+                    name, other.name
+                ),
+                Some(String::from(
+                    "names that only differ in dashes and underscores clash, choose another name",
+                )),
+            );
+            None
+        }
+        (name, _) => name,
+    };
+    let description = parse_string(
+        diagnostics,
+        table,
+        component.get("_description_"),
+        "_description_",
+        "component",
+    );
+
+    let attributes = match component.get("_attributes_") {
+        Some(attributes) => parse_attributes(diagnostics, table, attributes),
+        None => Some(Vec::new()),
+    };
+
+    let version = match component.get("_version_") {
+        Some(version) => parse_version(diagnostics, table, version),
+        None => Some(1),
+    };
+    let migrations = match (component.get("_migrations_"), version) {
+        (Some(migrations), Some(version)) => {
+            parse_migrations(diagnostics, scope, table, version, migrations)
+        }
+        (Some(_), None) => None,
+        (None, _) => Some(Vec::new()),
+    };
+
+    let fields: Vec<Option<Field>> = component
+        .iter()
+        .filter(|(k, _)| !is_reserved(k))
+        .map(|(field_name, field_type)| {
+            parse_field(diagnostics, scope, table, field_name, field_type)
+        })
+        .collect();
+    // Enums and named bits are declared inside of the component's struct
+    if let Some(name) = &name {
+        for field in fields.iter().flatten() {
+            let nested = matches!(field.data_type.element_type(), FieldType::Enum(_))
+                || !field.bits.is_empty();
+            if nested && field.type_name() == pascal_case(name) {
+                let location = diagnostics.locator.key(table, &field.name).or(table);
+                diagnostics.error(
+                    location,
+                    format!(
+                        "field \"{}\" declares a type with the same name as component \"{}\"",
+                        field.name, name
+                    ),
+                    Some(format!(
+                        "both are called {}, rename the field or the component",
+                        field.type_name()
+                    )),
+                );
+                return None;
+            }
+        }
+    }
+    if !fields.is_empty() && matches!(&attributes, Some(a) if a.contains(&Attribute::Tag)) {
+        diagnostics.error(
+            table,
+            format!(
+                "tag component \"{}\" cannot have fields",
+                name.as_deref().unwrap_or_default()
+            ),
+            Some(String::from(
+                "remove the fields, or remove \"tag\" from _attributes_",
+            )),
+        );
+        return None;
+    }
+
+    Some(Component {
+        name: name?,
+        namespace,
+        description,
+        attributes: attributes?,
+        fields: fields.into_iter().collect::<Option<Vec<Field>>>()?,
+        version: version?,
+        migrations: migrations?,
+    })
+}
+
+/// Names that cannot be given to a `[[type]]`, as fields could not refer to it
+const RESERVED_TYPE_NAMES: [&str; 6] = ["enum", "array", "string", "ptr", "resource", "struct"];
+
+/// Parses a `[[type]]`. Its fields can only use the types declared before it.
+fn parse_type(
+    diagnostics: &mut Diagnostics,
+    namespace: &str,
+    scope: &Scope,
+    index: usize,
+    struct_type: &Table,
+) -> Option<StructType> {
+    let table = Some(diagnostics.locator.array_table("type", index));
+    let location = diagnostics
+        .locator
+        .key(table, "_name_")
+        .map(|k| diagnostics.locator.value(k))
+        .or(table);
+    let name = match struct_type.get("_name_") {
+        Some(name) => parse_string(diagnostics, table, Some(name), "_name_", "type"),
+        None => {
+            diagnostics.error(
+                table,
+                String::from("type is missing its \"_name_\""),
+                Some(String::from(
+                    "every [[type]] needs a name, eg _name_ = \"damage-info\"",
+                )),
+            );
+            None
+        }
+    };
+    let name = match name {
+        Some(name) if !is_identifier(&name) => {
+            diagnostics.error(
+                location,
+                format!("type name \"{}\" is not a valid name", name),
+                Some(String::from(
+                    "use letters, digits and dashes, starting with a letter",
+                )),
+            );
+            None
+        }
+        Some(name)
+            if BUILTIN_TYPES.contains_key(&name)
+                || RESERVED_TYPE_NAMES.contains(&name.as_str()) =>
+        {
+            diagnostics.error(
+                location,
+                format!("type \"{}\" has the name of a built-in type", name),
+                Some(String::from("choose another name for the type")),
+            );
+            None
+        }
+        Some(name) if scope.types.iter().any(|x| x.name == name) => {
+            diagnostics.error(
+                location,
+                format!("type \"{}\" is declared more than once", name),
+                None,
+            );
+            None
+        }
+        name => name,
+    };
+    let description = parse_string(
+        diagnostics,
+        table,
+        struct_type.get("_description_"),
+        "_description_",
+        "type",
+    );
+
+    let fields: Vec<Option<Field>> = struct_type
+        .iter()
+        .filter(|(k, _)| !is_reserved(k))
+        .map(|(field_name, field_type)| {
+            parse_field(diagnostics, scope, table, field_name, field_type)
+        })
+        .collect();
+    if fields.is_empty() {
+        diagnostics.error(
+            table,
+            format!(
+                "type \"{}\" does not have any fields",
+                name.as_deref().unwrap_or_default()
+            ),
+            None,
+        );
+        return None;
+    }
+
+    Some(StructType {
+        name: name?,
+        namespace: namespace.to_string(),
+        imported: false,
+        description,
+        fields: fields.into_iter().collect::<Option<Vec<Field>>>()?,
+    })
+}
+
+/// Reads the namespaces named by the `imports` key of a components file
+pub fn parse_imports(diagnostics: &mut Diagnostics, defs: &Document) -> Option<Vec<String>> {
+    let item = match defs.get("imports") {
+        Some(item) => item,
+        None => return Some(Vec::new()),
+    };
+    let location = diagnostics
+        .locator
+        .key(None, "imports")
+        .map(|k| diagnostics.locator.value(k));
+    let imports = match item.as_array() {
+        Some(array) => array
+            .iter()
+            .map(|x| x.as_str().map(String::from))
+            .collect::<Option<Vec<String>>>(),
+        None => None,
+    };
+    let imports = match imports {
+        Some(imports) => imports,
+        None => {
+            diagnostics.error(
+                location,
+                String::from("\"imports\" must be a list of namespaces"),
+                Some(String::from("eg imports = [\"core\", \"physics\"]")),
+            );
+            return None;
+        }
+    };
+    let namespace = defs.get("namespace").and_then(Item::as_str);
+    let mut valid = true;
+    for (index, import) in imports.iter().enumerate() {
+        if Some(import.as_str()) == namespace {
+            diagnostics.error(
+                location,
+                format!("namespace \"{}\" imports itself", import),
+                None,
+            );
+            valid = false;
+        } else if imports[..index].contains(import) {
+            diagnostics.error(
+                location,
+                format!("namespace \"{}\" is imported twice", import),
+                None,
+            );
+            valid = false;
+        }
+    }
+    if valid {
+        Some(imports)
+    } else {
+        None
+    }
+}
+
+/// Builds the model of a components file, reporting every problem found to `diagnostics`.
+/// `imports` are the already loaded files of the namespaces it imports.
+/// Returns `None` if there were any errors.
+pub fn parse(
+    diagnostics: &mut Diagnostics,
+    defs: &Document,
+    imports: &[Arc<ComponentsFile>],
+) -> Option<ComponentsFile> {
+    let namespace = match defs.get("namespace") {
+        Some(namespace) => {
+            match parse_string(diagnostics, None, Some(namespace), "namespace", "the") {
+                // The namespace is used as is in C++ and Lua names, which can't contain dashes
+                Some(namespace) if !is_identifier(&namespace) || namespace.contains('-') => {
+                    let location = diagnostics
+                        .locator
+                        .key(None, "namespace")
+                        .map(|k| diagnostics.locator.value(k));
+                    diagnostics.error(
+                        location,
+                        format!("namespace \"{}\" is not a valid name", namespace),
+                        Some(String::from(
+                            "use letters, digits and underscores, starting with a letter",
+                        )),
+                    );
+                    None
+                }
+                namespace => namespace,
+            }
+        }
+        None => {
+            diagnostics.error(
+                Some(Location::default()),
+                String::from("components file must specify a namespace"),
+                Some(String::from(
+                    "add eg namespace = \"core\" to the top of the file",
+                )),
+            );
+            None
+        }
+    };
+
+    let mut types: Vec<Arc<StructType>> = Vec::new();
+    if let Some(item) = defs.get("type") {
+        match item.as_array_of_tables() {
+            Some(tables) => {
+                for (index, table) in tables.iter().enumerate() {
+                    let namespace = namespace.as_deref().unwrap_or_default();
+                    if let Some(struct_type) = parse_type(
+                        diagnostics,
+                        namespace,
+                        &Scope {
+                            types: &types,
+                            imports,
+                        },
+                        index,
+                        table,
+                    ) {
+                        types.push(Arc::new(struct_type));
+                    }
+                }
+            }
+            None => diagnostics.error(
+                diagnostics.locator.key(None, "type"),
+                String::from("\"type\" must be an array of tables"),
+                Some(String::from("declare each type with a [[type]] header")),
+            ),
+        }
+    }
+
+    let components = match defs.get("component") {
+        Some(item) => match item.as_array_of_tables() {
+            Some(tables) => {
+                let scope = Scope {
+                    types: &types,
+                    imports,
+                };
+                let mut components = Vec::new();
+                for (index, component) in tables.iter().enumerate() {
+                    let parsed =
+                        parse_component(diagnostics, &scope, index, component, &components);
+                    components.push(parsed);
+                }
+                components
+            }
+            None => {
+                diagnostics.error(
+                    diagnostics.locator.key(None, "component"),
+                    String::from("\"component\" must be an array of tables"),
+                    Some(String::from(
+                        "declare each component with a [[component]] header",
+                    )),
+                );
+                vec![None]
+            }
+        },
+        // Files can declare only types, for other namespaces to import
+        None => Vec::new(),
+    };
+
+    if diagnostics.has_errors() {
+        return None;
+    }
+    Some(ComponentsFile {
+        namespace: namespace?,
+        imports: imports.iter().map(|x| x.namespace.clone()).collect(),
+        types,
+        components: components.into_iter().collect::<Option<Vec<Component>>>()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the components file `source`, returning where each error was found and its
+    /// message if there were any
+    fn parse_str(source: &str) -> Result<ComponentsFile, Vec<(Option<Location>, String)>> {
+        let mut diagnostics = Diagnostics::new("components.toml", source);
+        let defs = source.parse::<Document>().unwrap();
+        parse(&mut diagnostics, &defs, &[]).ok_or_else(|| {
+            diagnostics
+                .errors
+                .into_iter()
+                .map(|error| (error.location, error.message))
+                .collect()
+        })
+    }
+
+    fn at(line: usize, column: usize, message: &str) -> (Option<Location>, String) {
+        (Some(Location { line, column }), String::from(message))
+    }
+
+    #[test]
+    fn accepts_files_without_components() {
+        let file = parse_str("namespace = \"test\"\n").unwrap();
+        assert_eq!(file.namespace, "test");
+        assert!(file.components.is_empty());
+    }
+
+    #[test]
+    fn checks_the_namespace() {
+        let errors = |source| parse_str(source).err().unwrap();
+        assert_eq!(
+            errors("namespace = \"my-game\"\n"),
+            [at(0, 12, "namespace \"my-game\" is not a valid name")]
+        );
+        assert_eq!(
+            errors("namespace = 3\n"),
+            [at(0, 12, "the \"namespace\" must be a string")]
+        );
+        assert_eq!(
+            errors("[[component]]\n    _name_ = \"a\"\n"),
+            [at(0, 0, "components file must specify a namespace")]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_and_mistyped_fields() {
+        let source = r#"namespace = "test"
+
+[[component]]
+    _name_  = "mover"
+    speed   = "flaot"
+    state   = { type = "enum" }
+    mass    = { type = "float", min = "low", deafult = 1.0 }
+    target  = 3
+
+[[component]]
+    speed = "float"
+"#;
+        assert_eq!(
+            parse_str(source).err().unwrap(),
+            [
+                at(4, 14, "unknown type \"flaot\" for field \"speed\""),
+                at(5, 23, "enum field \"state\" does not list its values"),
+                at(6, 45, "unknown key \"deafult\" in field \"mass\""),
+                at(6, 38, "\"min\" of field \"mass\" is not a valid \"float\""),
+                at(
+                    7,
+                    4,
+                    "\"target\" must be a string or a table naming the field type"
+                ),
+                at(9, 0, "component is missing its \"_name_\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_broken_migrations() {
+        let source = r#"namespace = "test"
+
+[[component]]
+    _name_       = "mover"
+    _version_    = 2
+    speed        = "float"
+    _migrations_ = [
+        { version = 3, rename = "velocity", to = "speed" },
+        { version = 2, split = "speed", into = "x" },
+    ]
+"#;
+        assert_eq!(
+            parse_str(source).err().unwrap(),
+            [
+                at(
+                    6,
+                    19,
+                    "rename migration is for version 3, but the component is at version 2"
+                ),
+                at(
+                    6,
+                    19,
+                    "\"into\" of split migration of \"speed\" must list a field name for each \
+                     component of the vector"
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use super::model::{Component, ComponentsFile, DefaultValue, Field, FieldType};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+const SCHEMA_DIALECT: &str = "http://json-schema.org/draft-07/schema#";
+
+/// Smallest and largest values of an integer type that can be written in TOML,
+/// whose integers are 64 bit signed
+fn integer_range(data_type: &FieldType) -> (i64, i64) {
+    let (minimum, maximum) = data_type
+        .integer_range()
+        .unwrap_or((i64::MIN as i128, i64::MAX as i128));
+    (minimum as i64, maximum.min(i64::MAX as i128) as i64)
+}
+
+/// Schema of a number of type `data_type`, limited to the range of `field`
+fn number_schema(field: &Field, data_type: &FieldType) -> Value {
+    let constraints = &field.constraints;
+    match data_type.integer_range() {
+        Some(_) => {
+            let (minimum, maximum) = integer_range(data_type);
+            json!({
+                "type": "integer",
+                "minimum": constraints.min.map_or(minimum, |min| min as i64),
+                "maximum": constraints.max.map_or(maximum, |max| max as i64),
+            })
+        }
+        None => {
+            let mut schema = Map::new();
+            schema.insert(String::from("type"), json!("number"));
+            if let Some(min) = constraints.min {
+                schema.insert(String::from("minimum"), json!(min));
+            }
+            if let Some(max) = constraints.max {
+                schema.insert(String::from("maximum"), json!(max));
+            }
+            Value::Object(schema)
+        }
+    }
+}
+
+/// The default value of a field, as it would be written in an entity file
+fn default_value(data_type: &FieldType, default: &DefaultValue) -> Value {
+    match default {
+        DefaultValue::Integer(value) => json!(value),
+        DefaultValue::Float(value) => json!(value),
+        DefaultValue::Bool(value) => json!(value),
+        DefaultValue::String(value) => json!(value),
+        DefaultValue::Vector(values) => Value::Object(
+            data_type
+                .vector_components()
+                .unwrap_or_default()
+                .iter()
+                .zip(values)
+                .map(|(name, value)| (String::from(*name), json!(value)))
+                .collect(),
+        ),
+        DefaultValue::Array(values) => {
+            let element = match data_type {
+                FieldType::Array(element, _) => element,
+                _ => data_type,
+            };
+            Value::Array(
+                values
+                    .iter()
+                    .map(|value| default_value(element, value))
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// Schema of the values the loader accepts for a field of type `data_type`
+fn value_schema(field: &Field, data_type: &FieldType) -> Value {
+    match data_type {
+        FieldType::Float | FieldType::Double => number_schema(field, data_type),
+        FieldType::Bool => json!({ "type": "boolean" }),
+        FieldType::HashedString | FieldType::Resource(_) => json!({ "type": "string" }),
+        FieldType::Ref | FieldType::Signal => json!({ "type": ["string", "integer"] }),
+        FieldType::Vec2 | FieldType::Vec3 | FieldType::Vec4 | FieldType::Rgb | FieldType::Rgba => {
+            let components = data_type.vector_components().unwrap();
+            json!({
+                "type": "object",
+                "properties": components
+                    .iter()
+                    .map(|name| {
+                        (
+                            String::from(*name),
+                            or_reference(number_schema(field, &FieldType::Float)),
+                        )
+                    })
+                    .collect::<Map<String, Value>>(),
+                "required": components,
+                "additionalProperties": false,
+            })
+        }
+        FieldType::Enum(values) => json!({ "type": "string", "enum": values }),
+        FieldType::String(size) => json!({ "type": "string", "maxLength": size - 1 }),
+        FieldType::Array(element, length) => json!({
+            "type": "array",
+            "items": or_reference(value_schema(field, element)),
+            "minItems": length,
+            "maxItems": length,
+        }),
+        FieldType::Struct(struct_type) => {
+            let mut schema = fields_schema(&struct_type.fields);
+            if let Some(description) = &struct_type.description {
+                schema.insert(String::from("description"), json!(description));
+            }
+            Value::Object(schema)
+        }
+        _ => {
+            let number = number_schema(field, data_type);
+            if field.bits.is_empty() {
+                number
+            } else {
+                json!({
+                    "anyOf": [
+                        number,
+                        {
+                            "type": "array",
+                            "items": { "type": "string", "enum": field.bits },
+                            "uniqueItems": true,
+                        },
+                    ]
+                })
+            }
+        }
+    }
+}
+
+/// Allows `schema`'s value to also be a template reference, which is replaced before
+/// the entity is loaded
+fn or_reference(schema: Value) -> Value {
+    json!({ "anyOf": [schema, { "$ref": "#/definitions/template-reference" }] })
+}
+
+/// Schema of a table holding `fields`
+fn fields_schema(fields: &[Field]) -> Map<String, Value> {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for field in fields.iter().filter(|field| {
+        !matches!(
+            field.data_type,
+            FieldType::Pointer(_) | FieldType::ImportedPointer(_)
+        )
+    }) {
+        let mut schema = value_schema(field, &field.data_type);
+        if let (Some(default), Value::Object(object)) = (&field.default, &mut schema) {
+            object.insert(
+                String::from("default"),
+                default_value(&field.data_type, default),
+            );
+        }
+        let mut schema = or_reference(schema);
+        // Editors show the description of the property itself, not of its alternatives
+        if let (Some(description), Value::Object(object)) = (&field.description, &mut schema) {
+            object.insert(String::from("description"), json!(description));
+        }
+        properties.insert(field.name.clone(), schema);
+        if field.is_required() {
+            required.push(field.name.clone());
+        }
+    }
+
+    let mut schema = Map::new();
+    schema.insert(String::from("type"), json!("object"));
+    schema.insert(String::from("properties"), Value::Object(properties));
+    if !required.is_empty() {
+        schema.insert(String::from("required"), json!(required));
+    }
+    schema.insert(String::from("additionalProperties"), json!(false));
+    schema
+}
+
+fn component_schema(component: &Component) -> Value {
+    let mut schema = fields_schema(&component.fields);
+    if let Some(description) = &component.description {
+        schema.insert(String::from("description"), json!(description));
+    }
+    Value::Object(schema)
+}
+
+/// Schema of an entity table, whose keys are the qualified names of the components
+/// in `files` plus the template's `metadata`
+fn entity_schema(files: &[Arc<ComponentsFile>]) -> Map<String, Value> {
+    let mut properties = Map::new();
+    properties.insert(
+        String::from("metadata"),
+        json!({ "type": "object", "description": "Template metadata, not a component" }),
+    );
+    for components in files {
+        for component in &components.components {
+            properties.insert(component.qualified_name(), component_schema(component));
+        }
+    }
+    let mut schema = Map::new();
+    schema.insert(String::from("type"), json!("object"));
+    schema.insert(String::from("properties"), Value::Object(properties));
+    schema.insert(String::from("additionalProperties"), json!(false));
+    schema
+}
+
+fn definitions() -> Value {
+    json!({
+        "template-reference": {
+            "type": "string",
+            "pattern": "^=> ",
+            "description": "Reference to template metadata, eg \"=> position.x\"",
+        }
+    })
+}
+
+/// Generates the JSON Schemas of entity files and of scene files, which are lists
+/// of entities, for the components declared by `files`
+pub fn generate(files: &[Arc<ComponentsFile>]) -> (String, String) {
+    let mut entity = Map::new();
+    entity.insert(String::from("$schema"), json!(SCHEMA_DIALECT));
+    entity.insert(String::from("title"), json!("Entity"));
+    entity.insert(
+        String::from("description"),
+        json!("Components of an entity, keyed by component name"),
+    );
+    entity.insert(String::from("definitions"), definitions());
+    entity.extend(entity_schema(files));
+    let scene = json!({
+        "$schema": SCHEMA_DIALECT,
+        "title": "Scene",
+        "description": "Entities of a scene",
+        "definitions": definitions(),
+        "type": "object",
+        "properties": {
+            "entity": { "type": "array", "items": entity_schema(files) },
+            "metadata": {
+                "type": "object",
+                "description": "Scene metadata, eg the versions of the components it was written for",
+            },
+        },
+    });
+    (
+        serde_json::to_string_pretty(&entity).unwrap() + "\n",
+        serde_json::to_string_pretty(&scene).unwrap() + "\n",
+    )
+}
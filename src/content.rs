@@ -0,0 +1,659 @@
+use crate::components::model::{suggest_name, Component, ComponentsFile, Field, FieldType};
+use crate::components::toml_error_hint;
+use crate::diagnostics::{Diagnostic, Diagnostics, Location, SourceLocator};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::Arc;
+use toml_edit::{Document, Item, Table, TableLike, Value};
+
+/// Prefix of the string values in prop templates that are replaced by template metadata
+pub(crate) const BINDING_PREFIX: &str = "=>";
+
+/// The version of the component called `name` that a content file was written for, as
+/// recorded in its `[metadata.versions]`. Files written before the component was versioned
+/// have no record of it, so are at version 1.
+pub fn recorded_version(root: &Table, name: &str) -> i64 {
+    root.get("metadata")
+        .and_then(|metadata| metadata.get("versions"))
+        .and_then(|versions| versions.get(name))
+        .and_then(Item::as_integer)
+        .unwrap_or(1)
+}
+
+/// What a content file holds, which decides where its entities are
+#[derive(Clone, Copy, PartialEq)]
+pub enum ContentType {
+    /// A list of `[[entity]]` tables
+    Scene,
+    /// A single entity, with `[metadata]` used by its bindings
+    Prop,
+}
+
+/// Where in the source an item was found. Keys of header tables are found by line,
+/// keys of inline tables are found on the line of their parent.
+#[derive(Clone, Copy)]
+struct Position {
+    location: Option<Location>,
+    header: bool,
+}
+
+impl Position {
+    fn child(&self, locator: &SourceLocator, key: &str) -> Position {
+        let location = match self.location {
+            Some(location) if self.header => locator.key(Some(location), key),
+            Some(location) => locator.inline_key(locator.value(location), key),
+            None => None,
+        };
+        Position {
+            location: location.or(self.location),
+            header: false,
+        }
+    }
+}
+
+/// Checks the entities of a scene or prop file against the component definitions
+struct Checker<'a> {
+    diagnostics: Diagnostics,
+    content_type: ContentType,
+    components: &'a HashMap<String, &'a Component>,
+    /// The `[metadata.data]` table of a prop, which bindings refer to
+    data: Option<&'a Item>,
+    /// The root table of the file, which records the versions of the components
+    root: &'a Table,
+    /// Components whose version has been checked, so each is only reported once per file
+    versions_checked: HashSet<String>,
+}
+
+/// Describes the values a field of type `data_type` accepts, for error messages
+fn expected(field: &Field, data_type: &FieldType) -> String {
+    match data_type {
+        FieldType::Float | FieldType::Double => String::from("a number"),
+        FieldType::Bool => String::from("true or false"),
+        FieldType::HashedString | FieldType::Resource(_) => String::from("a string"),
+        FieldType::Ref | FieldType::Signal => String::from("a name or a hash"),
+        FieldType::Enum(values) => format!("one of: {}", values.join(", ")),
+        FieldType::String(size) => format!("a string of at most {} bytes", size - 1),
+        FieldType::Array(element, length) => format!(
+            "an array of {} elements, each {}",
+            length,
+            expected(field, element)
+        ),
+        FieldType::Struct(struct_type) => format!("a {} table", struct_type.name),
+        FieldType::Vec2 | FieldType::Vec3 | FieldType::Vec4 | FieldType::Rgb | FieldType::Rgba => {
+            format!(
+                "a table of {} numbers",
+                data_type.vector_components().unwrap().join(", ")
+            )
+        }
+        _ => {
+            let (minimum, maximum) = data_type.integer_range().unwrap_or_default();
+            let integer = format!("an integer from {} to {}", minimum, maximum);
+            if field.bits.is_empty() {
+                integer
+            } else {
+                format!("{} or a list of: {}", integer, field.bits.join(", "))
+            }
+        }
+    }
+}
+
+impl<'a> Checker<'a> {
+    fn error(&mut self, position: Position, message: String, hint: Option<String>) {
+        self.diagnostics.error(position.location, message, hint);
+    }
+
+    /// Checks that a `=> path` binding names a key of `[metadata.data]`
+    fn check_binding(&mut self, position: Position, binding: &str) {
+        let path = binding.trim_start_matches(BINDING_PREFIX).trim();
+        if self.content_type == ContentType::Scene {
+            return self.error(
+                position,
+                format!("binding \"{}\" is used outside of a prop", path),
+                Some(String::from(
+                    "bindings refer to the [metadata.data] table of a prop's entity.toml",
+                )),
+            );
+        }
+        let mut item = self.data;
+        for key in path.split('.') {
+            item = item.and_then(|item| item.get(key));
+        }
+        if item.is_none() {
+            self.error(
+                position,
+                format!("binding \"{}\" is not a key of [metadata.data]", path),
+                None,
+            );
+        }
+    }
+
+    fn mistyped(&mut self, position: Position, field: &Field, data_type: &FieldType) {
+        self.error(
+            position,
+            format!("\"{}\" must be {}", field.name, expected(field, data_type)),
+            None,
+        );
+    }
+
+    /// Checks a number against the range the field's constraints allow
+    fn check_range(&mut self, position: Position, field: &Field, value: &Value) {
+        let number = value
+            .as_float()
+            .or_else(|| value.as_integer().map(|i| i as f64));
+        if let Some(number) = number {
+            if !field.constraints.contains(number) {
+                self.error(
+                    position,
+                    format!(
+                        "{} is out of range for \"{}\"",
+                        value.to_string().trim(),
+                        field.name
+                    ),
+                    Some(format!(
+                        "\"{}\" takes values {}",
+                        field.name,
+                        field.constraints.describe_range()
+                    )),
+                );
+            }
+        }
+    }
+
+    fn check_value(
+        &mut self,
+        position: Position,
+        field: &Field,
+        data_type: &FieldType,
+        value: &Value,
+    ) {
+        if let Value::String(binding) = value {
+            if binding.value().starts_with(BINDING_PREFIX) {
+                return self.check_binding(position, binding.value());
+            }
+        }
+        match data_type {
+            FieldType::Float | FieldType::Double => {
+                if !value.is_float() && !value.is_integer() {
+                    self.mistyped(position, field, data_type);
+                } else {
+                    self.check_range(position, field, value);
+                }
+            }
+            FieldType::Bool => {
+                if !value.is_bool() {
+                    self.mistyped(position, field, data_type);
+                }
+            }
+            FieldType::HashedString | FieldType::Resource(_) => {
+                if !value.is_str() {
+                    self.mistyped(position, field, data_type);
+                }
+            }
+            FieldType::Ref | FieldType::Signal => {
+                if !value.is_str() && !value.is_integer() {
+                    self.mistyped(position, field, data_type);
+                }
+            }
+            FieldType::Enum(values) => match value.as_str() {
+                Some(name) if values.iter().any(|x| x == name) => (),
+                Some(name) => self.error(
+                    position,
+                    format!("\"{}\" is not a value of \"{}\"", name, field.name),
+                    Some(suggest_name(
+                        name,
+                        values.iter().map(String::as_str).collect(),
+                        "values",
+                    )),
+                ),
+                None => self.mistyped(position, field, data_type),
+            },
+            FieldType::String(size) => match value.as_str() {
+                Some(text) if text.len() >= *size => self.error(
+                    position,
+                    format!(
+                        "\"{}\" is {} bytes long, but must be at most {}",
+                        field.name,
+                        text.len(),
+                        size - 1
+                    ),
+                    Some(String::from("the engine cuts longer strings short")),
+                ),
+                Some(_) => (),
+                None => self.mistyped(position, field, data_type),
+            },
+            FieldType::Array(element, length) => match value.as_array() {
+                Some(array) if array.len() == *length => {
+                    for element_value in array.iter() {
+                        self.check_value(position, field, element, element_value);
+                    }
+                }
+                _ => self.mistyped(position, field, data_type),
+            },
+            FieldType::Struct(struct_type) => match value.as_inline_table() {
+                Some(table) => self.check_fields(
+                    Position {
+                        location: position.location,
+                        header: false,
+                    },
+                    &struct_type.fields,
+                    table,
+                ),
+                None => self.mistyped(position, field, data_type),
+            },
+            FieldType::Vec2
+            | FieldType::Vec3
+            | FieldType::Vec4
+            | FieldType::Rgb
+            | FieldType::Rgba => {
+                let components = data_type.vector_components().unwrap();
+                let valid = value.as_inline_table().is_some_and(|table| {
+                    table.len() == components.len()
+                        && components.iter().all(|c| match table.get(c) {
+                            Some(Value::String(binding)) => {
+                                binding.value().starts_with(BINDING_PREFIX)
+                            }
+                            Some(number) => number.is_float() || number.is_integer(),
+                            None => false,
+                        })
+                });
+                if !valid {
+                    return self.mistyped(position, field, data_type);
+                }
+                for component in components {
+                    match value
+                        .as_inline_table()
+                        .and_then(|table| table.get(component))
+                    {
+                        Some(Value::String(binding)) => {
+                            self.check_binding(position, binding.value())
+                        }
+                        Some(number) => self.check_range(position, field, number),
+                        None => (),
+                    }
+                }
+            }
+            FieldType::Pointer(_) | FieldType::ImportedPointer(_) => (),
+            _ if !field.bits.is_empty() && value.is_array() => {
+                for bit in value.as_array().unwrap().iter() {
+                    match bit.as_str() {
+                        Some(name) if field.bits.iter().any(|x| x == name) => (),
+                        Some(name) => self.error(
+                            position,
+                            format!("\"{}\" is not a bit of \"{}\"", name, field.name),
+                            Some(suggest_name(
+                                name,
+                                field.bits.iter().map(String::as_str).collect(),
+                                "bits",
+                            )),
+                        ),
+                        None => self.mistyped(position, field, data_type),
+                    }
+                }
+            }
+            _ => {
+                let (minimum, maximum) = data_type.integer_range().unwrap_or_default();
+                match value.as_integer() {
+                    Some(integer) if (integer as i128) < minimum || (integer as i128) > maximum => {
+                        self.error(
+                            position,
+                            format!("{} is out of range for \"{}\"", integer, field.name),
+                            Some(format!(
+                                "\"{}\" is a {}, which holds {}",
+                                field.name,
+                                data_type.name(),
+                                expected(field, data_type).trim_start_matches("an ")
+                            )),
+                        )
+                    }
+                    Some(_) => self.check_range(position, field, value),
+                    None => self.mistyped(position, field, data_type),
+                }
+            }
+        }
+    }
+
+    /// Checks the keys of a component or struct table against `fields`
+    fn check_fields(&mut self, position: Position, fields: &[Field], table: &dyn TableLike) {
+        for (key, item) in table.iter() {
+            let child = position.child(&self.diagnostics.locator, key);
+            match fields.iter().find(|field| field.name == key) {
+                Some(field) => match item.clone().into_value() {
+                    Ok(value) => self.check_value(child, field, &field.data_type, &value),
+                    Err(_) => self.mistyped(child, field, &field.data_type),
+                },
+                None => self.error(
+                    child,
+                    format!("unknown field \"{}\"", key),
+                    Some(suggest_name(
+                        key,
+                        fields.iter().map(|field| field.name.as_str()).collect(),
+                        "fields",
+                    )),
+                ),
+            }
+        }
+        for field in fields.iter().filter(|field| field.is_required()) {
+            if !table.contains_key(&field.name) {
+                self.error(
+                    position,
+                    format!("missing required field \"{}\"", field.name),
+                    None,
+                );
+            }
+        }
+    }
+
+    /// Checks that the file was migrated to the version of the component it uses
+    fn check_version(&mut self, position: Position, component: &Component) {
+        let name = component.qualified_name();
+        if !self.versions_checked.insert(name.clone()) {
+            return;
+        }
+        let version = recorded_version(self.root, &name);
+        if version < component.version as i64 {
+            self.error(
+                position,
+                format!(
+                    "\"{}\" was written for version {} of the component, which is now at version {}",
+                    name, version, component.version
+                ),
+                Some(String::from(
+                    "run `mm project <name> migrate` to update the file",
+                )),
+            );
+        } else if version > component.version as i64 {
+            self.error(
+                position,
+                format!(
+                    "\"{}\" was written for version {} of the component, but the latest is version {}",
+                    name, version, component.version
+                ),
+                Some(String::from(
+                    "the file is newer than the components.toml files it is checked against",
+                )),
+            );
+        }
+    }
+
+    /// Checks every component of an entity table
+    fn check_entity(&mut self, position: Position, entity: &dyn TableLike) {
+        for (name, item) in entity.iter() {
+            if self.content_type == ContentType::Prop && name == "metadata" {
+                continue;
+            }
+            // Components of props are usually tables of their own
+            let child = match self.diagnostics.locator.table(name) {
+                Some(location) if self.content_type == ContentType::Prop => Position {
+                    location: Some(location),
+                    header: true,
+                },
+                _ => position.child(&self.diagnostics.locator, name),
+            };
+            let component = match self.components.get(name) {
+                Some(component) => *component,
+                None => {
+                    let hint = suggest_name(
+                        name,
+                        self.components.keys().map(String::as_str).collect(),
+                        "components",
+                    );
+                    self.error(child, format!("unknown component \"{}\"", name), Some(hint));
+                    continue;
+                }
+            };
+            self.check_version(child, component);
+            match item.as_table_like() {
+                Some(table) => self.check_fields(child, &component.fields, table),
+                None => self.error(
+                    child,
+                    format!("component \"{}\" must be a table", name),
+                    None,
+                ),
+            }
+        }
+    }
+}
+
+/// Maps the names used by content files to the components declared by `files`
+pub fn component_names(files: &[Arc<ComponentsFile>]) -> HashMap<String, &Component> {
+    let mut names = HashMap::new();
+    for components in files {
+        for component in &components.components {
+            names.entry(component.qualified_name()).or_insert(component);
+        }
+    }
+    names
+}
+
+/// Checks the `source` of a scene or prop file read from `path`, returning the errors found
+/// if there are any
+fn check_source(
+    path: &str,
+    source: &str,
+    content_type: ContentType,
+    components: &HashMap<String, &Component>,
+) -> Result<(), Vec<Diagnostic>> {
+    let mut diagnostics = Diagnostics::new(path, source);
+    let document = match source.parse::<Document>() {
+        Ok(document) => document,
+        Err(error) => {
+            diagnostics.error(
+                error
+                    .line_col()
+                    .map(|(line, column)| Location { line, column }),
+                String::from("content file is not valid TOML"),
+                toml_error_hint(&error),
+            );
+            return Err(diagnostics.errors);
+        }
+    };
+    let root: &Table = document.as_table();
+    let mut checker = Checker {
+        diagnostics,
+        content_type,
+        components,
+        data: root
+            .get("metadata")
+            .and_then(|metadata| metadata.get("data")),
+        root,
+        versions_checked: HashSet::new(),
+    };
+
+    match content_type {
+        ContentType::Prop => checker.check_entity(
+            Position {
+                location: None,
+                header: false,
+            },
+            root,
+        ),
+        ContentType::Scene => {
+            for (key, _) in root
+                .iter()
+                .filter(|(key, _)| *key != "entity" && *key != "metadata")
+            {
+                let location = checker.diagnostics.locator.key(None, key);
+                checker.error(
+                    Position {
+                        location,
+                        header: false,
+                    },
+                    format!("unexpected key \"{}\" in scene", key),
+                    Some(String::from("entities are listed as [[entity]] tables")),
+                );
+            }
+            if let Some(entities) = root.get("entity").and_then(Item::as_array_of_tables) {
+                for (index, entity) in entities.iter().enumerate() {
+                    let location = checker.diagnostics.locator.array_table("entity", index);
+                    checker.check_entity(
+                        Position {
+                            location: Some(location),
+                            header: true,
+                        },
+                        entity,
+                    );
+                }
+            }
+        }
+    }
+
+    if checker.diagnostics.has_errors() {
+        Err(checker.diagnostics.errors)
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks a scene or prop file, returning the errors found if there are any
+pub fn check(
+    path: &str,
+    content_type: ContentType,
+    components: &HashMap<String, &Component>,
+) -> Result<(), Vec<Diagnostic>> {
+    let source = fs::read_to_string(path).unwrap_or_default();
+    check_source(path, &source, content_type, components)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::model;
+
+    const COMPONENTS: &str = r#"
+namespace = "test"
+
+[[component]]
+    _name_ = "mover"
+    speed  = { type = "float", min = 0.0, max = 10.0 }
+    state  = { type = "enum", values = ["idle", "walking"], default = "idle" }
+    target = "vec3"
+    count  = { type = "uint8", optional = true }
+
+[[component]]
+    _name_    = "named"
+    _version_ = 2
+    name      = "hashed-string"
+    _migrations_ = [{ version = 2, rename = "label", to = "name" }]
+"#;
+
+    fn components() -> Arc<ComponentsFile> {
+        let mut diagnostics = Diagnostics::new("components.toml", COMPONENTS);
+        let defs = COMPONENTS.parse::<Document>().unwrap();
+        let file = model::parse(&mut diagnostics, &defs, &[]);
+        assert!(diagnostics.errors.is_empty());
+        Arc::new(file.unwrap())
+    }
+
+    /// Checks the content `source`, returning where each error was found and its message
+    fn check_str(content_type: ContentType, source: &str) -> Vec<(Location, String)> {
+        let files = [components()];
+        match check_source(
+            "content.toml",
+            source,
+            content_type,
+            &component_names(&files),
+        ) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .into_iter()
+                .map(|error| (error.location.unwrap(), error.message))
+                .collect(),
+        }
+    }
+
+    fn at(line: usize, column: usize, message: &str) -> (Location, String) {
+        (Location { line, column }, String::from(message))
+    }
+
+    #[test]
+    fn accepts_valid_content() {
+        let scene = r#"[metadata.versions]
+    named = 2
+
+[[entity]]
+    named = { name = "one" }
+    mover = { speed = 10, state = "walking", target = { x = 1, y = 2.5, z = 0 } }
+"#;
+        assert_eq!(check_str(ContentType::Scene, scene), []);
+    }
+
+    #[test]
+    fn reports_unknown_and_mistyped_fields() {
+        let scene = r#"[[entity]]
+    mover = { speed = 11, state = "running", target = { x = 1, y = 2 }, sped = 1.0 }
+
+[[entity]]
+    mover = { speed = true, state = 3, target = { x = 1, y = 2, z = 3 }, count = 256 }
+    mouse = {}
+"#;
+        assert_eq!(
+            check_str(ContentType::Scene, scene),
+            [
+                at(1, 14, "11 is out of range for \"speed\""),
+                at(1, 26, "\"running\" is not a value of \"state\""),
+                at(1, 45, "\"target\" must be a table of x, y, z numbers"),
+                at(1, 72, "unknown field \"sped\""),
+                at(4, 14, "\"speed\" must be a number"),
+                at(4, 28, "\"state\" must be one of: idle, walking"),
+                at(4, 73, "256 is out of range for \"count\""),
+                at(5, 4, "unknown component \"mouse\""),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_missing_required_fields() {
+        // Fields with a default or marked optional can be left out
+        let scene = "[[entity]]\n    mover = { speed = 1.0 }\n";
+        assert_eq!(
+            check_str(ContentType::Scene, scene),
+            [at(1, 4, "missing required field \"target\"")]
+        );
+    }
+
+    #[test]
+    fn checks_bindings_against_the_prop_metadata() {
+        let prop = r#"[metadata.data]
+    position = { x = 0, y = 0, z = 0 }
+
+[mover]
+    speed  = "=> speed"
+    target = "=> position"
+    state  = "=> position.w"
+"#;
+        assert_eq!(
+            check_str(ContentType::Prop, prop),
+            [
+                at(4, 4, "binding \"speed\" is not a key of [metadata.data]"),
+                at(
+                    6,
+                    4,
+                    "binding \"position.w\" is not a key of [metadata.data]"
+                ),
+            ]
+        );
+        let scene =
+            "[[entity]]\n    mover = { speed = \"=> speed\", target = { x = 1, y = 2, z = 3 } }\n";
+        assert_eq!(
+            check_str(ContentType::Scene, scene),
+            [at(1, 14, "binding \"speed\" is used outside of a prop")]
+        );
+    }
+
+    #[test]
+    fn reports_components_that_were_not_migrated() {
+        let prop = "[named]\n    label = \"old\"\n";
+        assert_eq!(
+            check_str(ContentType::Prop, prop),
+            [
+                at(
+                    0,
+                    0,
+                    "\"named\" was written for version 1 of the component, which is now at version 2"
+                ),
+                at(1, 4, "unknown field \"label\""),
+                at(0, 0, "missing required field \"name\""),
+            ]
+        );
+    }
+}
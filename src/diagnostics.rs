@@ -0,0 +1,220 @@
+use colored::*;
+use regex::Regex;
+use std::fs;
+
+/// A position in a source file. Both fields are 0-based, they are converted to
+/// 1-based numbers when reported.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub file: String,
+    pub location: Option<Location>,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+/// Finds the position of tables and keys in the original TOML text, since
+/// toml_edit does not keep track of where items were parsed from.
+pub struct SourceLocator {
+    lines: Vec<String>,
+}
+
+impl SourceLocator {
+    pub fn new(source: &str) -> SourceLocator {
+        SourceLocator {
+            lines: source.lines().map(String::from).collect(),
+        }
+    }
+
+    fn is_header(line: &str) -> bool {
+        line.trim_start().starts_with('[')
+    }
+
+    /// Location of the `index`th `[[name]]` header
+    pub fn array_table(&self, name: &str, index: usize) -> Location {
+        let header =
+            Regex::new(&format!(r#"^\s*\[\[\s*"?{}"?\s*\]\]"#, regex::escape(name))).unwrap();
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| header.is_match(line))
+            .nth(index)
+            .map(|(line, text)| Location {
+                line,
+                column: text.len() - text.trim_start().len(),
+            })
+            .unwrap_or_default()
+    }
+
+    /// Location of the `[name]` header
+    pub fn table(&self, name: &str) -> Option<Location> {
+        let header = Regex::new(&format!(r#"^\s*\[\s*"?{}"?\s*\]"#, regex::escape(name))).unwrap();
+        self.lines
+            .iter()
+            .enumerate()
+            .find(|(_, line)| header.is_match(line))
+            .map(|(line, text)| Location {
+                line,
+                column: text.len() - text.trim_start().len(),
+            })
+    }
+
+    /// Location of `key` inside the table whose header is at `table`. Pass `None`
+    /// to look up a key at the top of the document, before the first header.
+    pub fn key(&self, table: Option<Location>, key: &str) -> Option<Location> {
+        let pattern = Regex::new(&format!(
+            r#"^\s*(["']?){}(["']?)\s*[=.]"#,
+            regex::escape(key)
+        ))
+        .unwrap();
+        let start = table.map(|t| t.line + 1).unwrap_or(0);
+        self.lines
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take_while(|(_, line)| !Self::is_header(line))
+            .find(|(_, line)| pattern.is_match(line))
+            .map(|(line, text)| Location {
+                line,
+                column: text.len() - text.trim_start().len(),
+            })
+    }
+
+    /// Location of the value assigned to the key at `key`
+    pub fn value(&self, key: Location) -> Location {
+        let text = &self.lines[key.line];
+        match text[key.column..].find('=') {
+            Some(offset) => {
+                let after = &text[key.column + offset + 1..];
+                Location {
+                    line: key.line,
+                    column: key.column + offset + 1 + (after.len() - after.trim_start().len()),
+                }
+            }
+            None => key,
+        }
+    }
+
+    /// Location of `key` inside an inline table that starts on the line of `parent`
+    pub fn inline_key(&self, parent: Location, key: &str) -> Option<Location> {
+        let pattern = Regex::new(&format!(
+            r#"[{{,]\s*(["']?){}(["']?)\s*="#,
+            regex::escape(key)
+        ))
+        .unwrap();
+        let text = &self.lines[parent.line];
+        pattern.find(&text[parent.column..]).map(|m| {
+            let matched = m.as_str();
+            let skipped = matched.len() - matched[1..].trim_start().len();
+            Location {
+                line: parent.line,
+                column: parent.column + m.start() + skipped,
+            }
+        })
+    }
+
+    pub fn line(&self, line: usize) -> Option<&str> {
+        self.lines.get(line).map(String::as_str)
+    }
+}
+
+/// Collects errors found in a single source file so they can all be reported at once
+pub struct Diagnostics {
+    pub file: String,
+    pub locator: SourceLocator,
+    pub errors: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new(file: &str, source: &str) -> Diagnostics {
+        Diagnostics {
+            file: file.to_string(),
+            locator: SourceLocator::new(source),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn error(&mut self, location: Option<Location>, message: String, hint: Option<String>) {
+        self.errors.push(Diagnostic {
+            file: self.file.clone(),
+            location,
+            message,
+            hint,
+        });
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    pub fn report(&self) {
+        for error in &self.errors {
+            eprintln!("{}: {}", "error".red().bold(), error.message.bold());
+            match error.location {
+                Some(location) => {
+                    let line_number = (location.line + 1).to_string();
+                    let gutter = " ".repeat(line_number.len());
+                    eprintln!(
+                        "{}{} {}:{}:{}",
+                        gutter,
+                        "-->".blue().bold(),
+                        error.file,
+                        location.line + 1,
+                        location.column + 1
+                    );
+                    if let Some(text) = self.locator.line(location.line) {
+                        eprintln!("{} {}", gutter, "|".blue().bold());
+                        eprintln!(
+                            "{} {} {}",
+                            line_number.blue().bold(),
+                            "|".blue().bold(),
+                            text
+                        );
+                        eprintln!(
+                            "{} {} {}{}",
+                            gutter,
+                            "|".blue().bold(),
+                            " ".repeat(location.column),
+                            "^".red().bold()
+                        );
+                    }
+                    if let Some(hint) = &error.hint {
+                        eprintln!("{} {} hint: {}", gutter, "=".blue().bold(), hint);
+                    }
+                }
+                None => {
+                    eprintln!(" {} {}", "-->".blue().bold(), error.file);
+                    if let Some(hint) = &error.hint {
+                        eprintln!("  {} hint: {}", "=".blue().bold(), hint);
+                    }
+                }
+            }
+            eprintln!();
+        }
+        if self.has_errors() {
+            eprintln!(
+                "{}: could not process {} due to {} previous error{}",
+                "error".red().bold(),
+                self.file,
+                self.errors.len(),
+                if self.errors.len() == 1 { "" } else { "s" }
+            );
+        }
+    }
+}
+
+/// Reports diagnostics collected from any number of files, showing the source lines they
+/// point at. Consecutive diagnostics of the same file are reported together.
+pub fn report_all(diagnostics: &[Diagnostic]) {
+    for group in diagnostics.chunk_by(|a, b| a.file == b.file) {
+        let source = fs::read_to_string(&group[0].file).unwrap_or_default();
+        let mut file = Diagnostics::new(&group[0].file, &source);
+        file.errors = group.to_vec();
+        file.report();
+    }
+}
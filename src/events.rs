@@ -1,10 +1,12 @@
+use crate::components::model::{suggest_name, FieldType};
+use crate::components::{lua_type, toml_error_hint};
+use crate::diagnostics::{Diagnostic, Diagnostics, Location};
 use crate::utils::*;
 use case_style::CaseStyle;
 use colored::*;
 use phf::phf_map;
-use std::collections::HashMap;
 use std::fs;
-use toml_edit::{value, Document, Item, Table};
+use toml_edit::Document;
 
 struct Types {
     types: phf::Map<&'static str, &'static str>,
@@ -14,11 +16,11 @@ static DATA_TYPES_CPP: Types = Types {
     types: phf_map! {
         "entity" => "entt::entity",
         "uint8" => "std::uint8_t",
-        "uint16" => "std::uint15_t",
+        "uint16" => "std::uint16_t",
         "uint32" => "std::uint32_t",
         "uint64" => "std::uint64_t",
         "int8" => "std::int8_t",
-        "int16" => "std::int15_t",
+        "int16" => "std::int16_t",
         "int32" => "std::int32_t",
         "int64" => "std::int64_t",
         "byte" => "std::byte",
@@ -38,11 +40,11 @@ static DATA_TYPES_LUA: Types = Types {
     types: phf_map! {
         "entity" => "uint32_t",
         "uint8" => "uint8_t",
-        "uint16" => "uint15_t",
+        "uint16" => "uint16_t",
         "uint32" => "uint32_t",
         "uint64" => "uint64_t",
         "int8" => "int8_t",
-        "int16" => "int15_t",
+        "int16" => "int16_t",
         "int32" => "int32_t",
         "int64" => "int64_t",
         "byte" => "uint8_t",
@@ -59,33 +61,29 @@ static DATA_TYPES_LUA: Types = Types {
     },
 };
 
+/// An event of the events file, with the names and types of its fields
+struct Event {
+    name: String,
+    fields: Vec<(String, FieldType)>,
+}
+
 impl Types {
-    fn generate_event_field(&self, (field_name, field_type): (&str, &Item)) -> String {
-        let data_type = field_type
-            .as_str()
-            .expect(format!("\"{}\" must be a string naming the field type", field_name).as_str());
+    fn generate_event_field(&self, (field_name, field_type): &(String, FieldType)) -> String {
         format!(
             "    {} {};",
-            self.types
-                .get(data_type)
-                .expect("\"{}\" must name a valid field data type"),
+            self.types.get(field_type.name()).unwrap(),
             field_name
         )
     }
 }
 
-fn generate_event(
-    types: &Types,
-    name_suffix: &str,
-    (event_name, event_fields): (&str, &Item),
-) -> String {
+fn generate_event(types: &Types, name_suffix: &str, event: &Event) -> String {
     format!(
         "struct {}{} {{\n{}\n}};",
-        CaseStyle::from_kebabcase(event_name).to_pascalcase(),
+        CaseStyle::from_kebabcase(&event.name).to_pascalcase(),
         name_suffix,
-        event_fields
-            .as_table()
-            .expect(format!("\"{}\" must be a TOML table", event_name).as_str())
+        event
+            .fields
             .iter()
             .map(|x| types.generate_event_field(x))
             .collect::<Vec<String>>()
@@ -93,7 +91,7 @@ fn generate_event(
     )
 }
 
-fn generate_events<'a>(types: &Types, name_suffix: &str, events: &Document) -> String {
+fn generate_events(types: &Types, name_suffix: &str, events: &[Event]) -> String {
     events
         .iter()
         .map(|x| generate_event(types, name_suffix, x))
@@ -101,15 +99,34 @@ fn generate_events<'a>(types: &Types, name_suffix: &str, events: &Document) -> S
         .join("\n")
 }
 
-fn generate_event_name_pair((event_name, _): (&str, &Item)) -> String {
+fn generate_event_annotation(event: &Event) -> String {
+    let mut lines = vec![format!(
+        "---@class {}_Event",
+        CaseStyle::from_kebabcase(&event.name).to_pascalcase()
+    )];
+    lines.extend(event.fields.iter().map(|(field_name, field_type)| {
+        format!("---@field {} {}", field_name, lua_type(field_type))
+    }));
+    lines.join("\n")
+}
+
+fn generate_event_annotations(events: &[Event]) -> String {
+    events
+        .iter()
+        .map(generate_event_annotation)
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+fn generate_event_name_pair(event: &Event) -> String {
     format!(
         "\t{{name='{}', type='{}_Event'}},",
-        event_name,
-        CaseStyle::from_kebabcase(event_name).to_pascalcase(),
+        event.name,
+        CaseStyle::from_kebabcase(&event.name).to_pascalcase(),
     )
 }
 
-fn generate_events_map(events: &Document) -> String {
+fn generate_events_map(events: &[Event]) -> String {
     events
         .iter()
         .map(generate_event_name_pair)
@@ -117,24 +134,152 @@ fn generate_events_map(events: &Document) -> String {
         .join("\n")
 }
 
-pub fn generate(source: &str, generate_lua: bool, generate_header: bool, output_dir: &str) {
-    let events = fs::read_to_string(source)
-        .expect(format!("Events file {} must exist", source).as_str())
-        .parse::<Document>()
-        .expect(format!("Events file {} must be valid TOML file", source).as_str());
+/// Reads the events of `events`, reporting events that are not tables and fields that
+/// do not name a type that events can hold
+fn parse_events(diagnostics: &mut Diagnostics, events: &Document) -> Vec<Event> {
+    let mut parsed = Vec::new();
+    for (event_name, item) in events.iter() {
+        let fields = match item.as_table() {
+            Some(fields) => fields,
+            None => {
+                let location = diagnostics.locator.key(None, event_name);
+                diagnostics.error(
+                    location,
+                    format!("event \"{}\" must be a table", event_name),
+                    Some(format!(
+                        "declare its fields under an [{}] header",
+                        event_name
+                    )),
+                );
+                continue;
+            }
+        };
+        let table = diagnostics.locator.table(event_name);
+        let mut event = Event {
+            name: event_name.to_string(),
+            fields: Vec::new(),
+        };
+        for (field_name, field_type) in fields.iter() {
+            let location = diagnostics
+                .locator
+                .key(table, field_name)
+                .map(|k| diagnostics.locator.value(k));
+            match field_type.as_str() {
+                Some(type_name) if DATA_TYPES_CPP.types.contains_key(type_name) => {
+                    let data_type = FieldType::builtin(type_name).unwrap();
+                    event.fields.push((field_name.to_string(), data_type))
+                }
+                Some(type_name) => diagnostics.error(
+                    location,
+                    format!(
+                        "unknown type \"{}\" for field \"{}\" of event \"{}\"",
+                        type_name, field_name, event_name
+                    ),
+                    Some(suggest_name(
+                        type_name,
+                        DATA_TYPES_CPP.types.keys().copied().collect(),
+                        "types",
+                    )),
+                ),
+                None => diagnostics.error(
+                    location,
+                    format!(
+                        "\"{}\" of event \"{}\" must be a string naming the field type",
+                        field_name, event_name
+                    ),
+                    Some(format!("eg {} = \"float\"", field_name)),
+                ),
+            }
+        }
+        parsed.push(event);
+    }
+    parsed
+}
+
+/// Reads and parses the events file `source`, returning the problems found if it can't be
+fn parse(source: &str) -> Result<Vec<Event>, Vec<Diagnostic>> {
+    let text = match fs::read_to_string(source) {
+        Ok(text) => text,
+        Err(error) => {
+            let mut diagnostics = Diagnostics::new(source, "");
+            diagnostics.error(None, format!("could not read events file: {}", error), None);
+            return Err(diagnostics.errors);
+        }
+    };
+    let mut diagnostics = Diagnostics::new(source, &text);
+    match text.parse::<Document>() {
+        Ok(events) => {
+            let events = parse_events(&mut diagnostics, &events);
+            if diagnostics.has_errors() {
+                Err(diagnostics.errors)
+            } else {
+                Ok(events)
+            }
+        }
+        Err(error) => {
+            diagnostics.error(
+                error
+                    .line_col()
+                    .map(|(line, column)| Location { line, column }),
+                String::from("events file is not valid TOML"),
+                toml_error_hint(&error),
+            );
+            Err(diagnostics.errors)
+        }
+    }
+}
+
+/// The Lua definition and annotations of the events
+fn lua_files(events: &[Event], template_dirs: &[String]) -> Result<Vec<GeneratedFile>, Diagnostic> {
+    Ok(vec![
+        GeneratedFile {
+            name: String::from("events.lua"),
+            contents: template_to_str(
+                "project/events.lua",
+                &liquid::object!({
+                    "cdef":  generate_events(&DATA_TYPES_LUA, "_Event", events),
+                    "event_types_map": generate_events_map(events),
+                }),
+                template_dirs,
+            )?,
+        },
+        GeneratedFile {
+            name: String::from("events.d.lua"),
+            contents: template_to_str(
+                "project/events.d.lua",
+                &liquid::object!({
+                    "classes": generate_event_annotations(events),
+                }),
+                template_dirs,
+            )?,
+        },
+    ])
+}
+
+/// Generates the Lua definition and annotations of the events file `source`, without
+/// writing them. Templates are looked up in `template_dirs` before the embedded ones.
+pub fn generate_files(source: &str, template_dirs: &[String]) -> Generated {
+    match parse(source) {
+        Ok(events) => lua_files(&events, template_dirs).into(),
+        Err(errors) => Generated::failed(errors),
+    }
+}
+
+pub fn generate(
+    source: &str,
+    generate_lua: bool,
+    generate_header: bool,
+    output_dir: &str,
+    template_dirs: &[String],
+) -> Result<(), Vec<Diagnostic>> {
+    let events = parse(source)?;
     if generate_lua {
-        println!(
-            "Outputting to: {}",
-            format!("{}/events.lua", output_dir).blue()
-        );
-        make_file(
-            &format!("{}/events.lua", output_dir),
-            "project/events.lua",
-            &liquid::object!({
-                "cdef":  generate_events(&DATA_TYPES_LUA, "_Event", &events),
-                "event_types_map": generate_events_map(&events),
-            }),
-        );
+        let files = lua_files(&events, template_dirs).map_err(|error| vec![error])?;
+        for file in files {
+            let path = output_path(output_dir, &file.name);
+            println!("Outputting to: {}", path.blue());
+            update_file_with_str(&path, &file.contents).map_err(|error| vec![error])?;
+        }
     }
     if generate_header {
         let events_code = generate_events(&DATA_TYPES_CPP, "", &events);
@@ -145,4 +290,5 @@ pub fn generate(source: &str, generate_lua: bool, generate_header: bool, output_
             format!("{}/events.hpp", output_dir).blue()
         );
     }
+    Ok(())
 }
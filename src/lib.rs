@@ -0,0 +1,33 @@
+//! Code generators and project tools of the Million Monkeys engine, as used by `mm`.
+//!
+//! The `generate_*files` functions return the generated sources as strings, along with
+//! any problems found in the input, instead of writing files and exiting, so that build
+//! scripts and other tools can run the generators in-process:
+//!
+//! ```no_run
+//! use million_monkeys_cli::components::{self, GeneratorType, Options};
+//!
+//! let generated = components::generate_files(
+//!     "game/components.toml",
+//!     GeneratorType::HeaderFile,
+//!     &Options::default(),
+//! );
+//! for diagnostic in &generated.diagnostics {
+//!     eprintln!("{}: {}", diagnostic.file, diagnostic.message);
+//! }
+//! if let Some(header) = generated.file("game.hpp") {
+//!     std::fs::write("include/game.hpp", header).unwrap();
+//! }
+//! ```
+
+pub mod components;
+pub mod content;
+pub mod diagnostics;
+pub mod events;
+pub mod migration;
+pub mod module;
+pub mod project;
+pub mod templates;
+pub mod utils;
+
+pub use utils::{Generated, GeneratedFile};
@@ -1,15 +1,7 @@
 use clap::ArgEnum;
-use clap::{Parser, Subcommand};
-use strum_macros::Display;
-
-#[macro_use]
-extern crate maplit;
-
-pub mod components;
-pub mod events;
-pub mod module;
-pub mod project;
-pub mod utils;
+use clap::{Args, Parser, Subcommand};
+use million_monkeys_cli::diagnostics::{report_all, Diagnostic};
+use million_monkeys_cli::{components, module, project, templates, utils};
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
@@ -47,6 +39,16 @@ enum Commands {
         #[clap(subcommand)]
         command: GenerateCommands,
     },
+    /// Customise the templates that code and projects are generated from
+    Templates {
+        /// Use the templates of this project, instead of the templates directory in the
+        /// current directory
+        #[clap(short, long, value_name = "PROJECT")]
+        project: Option<String>,
+
+        #[clap(subcommand)]
+        command: TemplatesCommands,
+    },
 }
 
 #[derive(Subcommand)]
@@ -72,6 +74,10 @@ enum ProjectCommands {
     },
     /// Create a release package for a project
     Release,
+    /// Check the project's scenes and props against the component definitions
+    CheckContent,
+    /// Migrate the project's scenes and props to the latest versions of the components
+    Migrate,
 }
 
 #[derive(Subcommand)]
@@ -82,6 +88,23 @@ enum ModuleCommands {
     Build,
 }
 
+#[derive(Subcommand)]
+enum TemplatesCommands {
+    /// List the templates, and the files overriding them
+    List,
+    /// Copy a built in template into the templates directory, to customise it
+    Eject {
+        /// Template name, as shown by `mm templates list`
+        name: String,
+        /// Copy it to the user's templates directory, to customise it for every project
+        #[clap(long)]
+        user: bool,
+        /// Replace the template if it has already been ejected
+        #[clap(long)]
+        force: bool,
+    },
+}
+
 #[derive(Subcommand)]
 enum GenerateCommands {
     /// Generate code from TOML definition
@@ -92,7 +115,70 @@ enum GenerateCommands {
         source: String,
         /// Destination directory
         destination: String,
+        #[clap(flatten)]
+        options: GenerateOptions,
+    },
+    /// Generate the header, Lua and C++ files for many TOML definitions at once
+    All {
+        /// Destination directory
+        destination: String,
+        /// Source TOML files, or directories to search for components.toml files
+        #[clap(required = true)]
+        sources: Vec<String>,
+        #[clap(flatten)]
+        options: GenerateOptions,
+    },
+    /// Report the memory layout of the generated structs and check that C++ and Lua agree
+    Layout {
+        /// Source TOML files, or directories to search for components.toml files
+        #[clap(required = true)]
+        sources: Vec<String>,
+        /// Components files, or directories to search for them, that imports are looked up in
+        #[clap(short = 'I', long = "import-path", value_name = "PATH")]
+        import_path: Vec<String>,
     },
+    /// Generate JSON Schemas for entity and scene files from TOML definitions
+    Schema {
+        /// Destination directory
+        destination: String,
+        /// Source TOML files, or directories to search for components.toml files
+        #[clap(required = true)]
+        sources: Vec<String>,
+        /// Components files, or directories to search for them, that imports are looked up in
+        #[clap(short = 'I', long = "import-path", value_name = "PATH")]
+        import_path: Vec<String>,
+        /// Print a diff and fail if the schemas would change, instead of writing them
+        #[clap(long)]
+        check: bool,
+    },
+}
+
+#[derive(Args)]
+struct GenerateOptions {
+    /// Components files, or directories to search for them, that imports are looked up in
+    #[clap(short = 'I', long = "import-path", value_name = "PATH")]
+    import_path: Vec<String>,
+    /// Add Lua metatypes, for component constructors and vector arithmetic
+    #[clap(long)]
+    metatypes: bool,
+    /// Also generate the ImGui inspectors, when generating every file
+    #[clap(long)]
+    inspectors: bool,
+    /// Print a diff and fail if the generated files would change, instead of writing them
+    #[clap(long)]
+    check: bool,
+}
+
+impl GenerateOptions {
+    fn to_options(&self, template_dirs: &[String]) -> components::Options {
+        components::Options {
+            import_path: self.import_path.clone(),
+            metatypes: self.metatypes,
+            inspectors: self.inspectors,
+            check: self.check,
+            template_dirs: template_dirs.to_vec(),
+        }
+    }
 }
 
 #[derive(Clone, ArgEnum)]
@@ -100,53 +186,157 @@ enum GeneratorTypes {
     Lua,
     Hpp,
     Cpp,
+    /// ImGui editors for the components
+    Inspectors,
 }
 
 fn main() {
     let cli = Cli::parse();
-    match &cli.command {
+    // Templates are looked up in the project, then for the user, before the embedded ones
+    let project_templates = match &cli.command {
+        Commands::Project { name, .. } => project::templates_dir(name),
+        Commands::Module {
+            project: Some(project),
+            ..
+        }
+        | Commands::Templates {
+            project: Some(project),
+            ..
+        } => project::templates_dir(project),
+        _ => String::from("templates"),
+    };
+    let template_dirs = utils::template_dirs(&project_templates);
+
+    let result = match &cli.command {
         Commands::Project { name, command } => match command {
-            ProjectCommands::Create => project::create(name),
-            ProjectCommands::Build { builddir } => project::build(name, builddir),
-            ProjectCommands::Dev => project::dev(name),
-            ProjectCommands::Run { build, loglevel } => project::run(
-                name,
-                &build.unwrap_or(project::RunBuild::Release),
-                &loglevel,
-            ),
-            ProjectCommands::Release => project::release(name),
+            ProjectCommands::Create => {
+                project::create(name, &template_dirs).map_err(|error| vec![error])
+            }
+            ProjectCommands::Build { builddir } => project::build(name, builddir, &template_dirs),
+            ProjectCommands::Dev => {
+                project::dev(name);
+                Ok(())
+            }
+            ProjectCommands::Run { build, loglevel } => {
+                project::run(name, &build.unwrap_or(project::RunBuild::Release), loglevel);
+                Ok(())
+            }
+            ProjectCommands::Release => {
+                project::release(name);
+                Ok(())
+            }
+            ProjectCommands::CheckContent => project::check_content(name),
+            ProjectCommands::Migrate => project::migrate(name),
         },
-        Commands::Dev {} => println!("NOT IMPLEMENTED: dev"),
+        Commands::Dev {} => {
+            println!("NOT IMPLEMENTED: dev");
+            Ok(())
+        }
         Commands::Module {
             name,
             project,
             command,
         } => match command {
-            ModuleCommands::New => module::new(name, project),
-            ModuleCommands::Build => module::build(name, project),
+            ModuleCommands::New => {
+                module::new(name, project, &template_dirs).map_err(|error| vec![error])
+            }
+            ModuleCommands::Build => {
+                module::build(name, project);
+                Ok(())
+            }
+        },
+        Commands::Templates { command, .. } => match command {
+            TemplatesCommands::List => {
+                templates::list(&template_dirs);
+                Ok(())
+            }
+            TemplatesCommands::Eject { name, user, force } => {
+                let directory = if *user {
+                    utils::user_templates_dir().unwrap_or_else(|| {
+                        eprintln!("error: could not find the user's configuration directory");
+                        std::process::exit(1)
+                    })
+                } else {
+                    project_templates
+                };
+                templates::eject(name, &directory, *force)
+            }
         },
         Commands::Generate { command } => match command {
             GenerateCommands::Components {
                 build,
                 source,
                 destination,
+                options,
             } => match build {
                 GeneratorTypes::Lua => components::generate(
                     source,
                     components::GeneratorType::LuaDefinitions,
                     destination,
+                    &options.to_options(&template_dirs),
                 ),
                 GeneratorTypes::Cpp => components::generate(
                     source,
                     components::GeneratorType::CppDefinitions,
                     destination,
+                    &options.to_options(&template_dirs),
+                ),
+                GeneratorTypes::Hpp => components::generate(
+                    source,
+                    components::GeneratorType::HeaderFile,
+                    destination,
+                    &options.to_options(&template_dirs),
+                ),
+                GeneratorTypes::Inspectors => components::generate(
+                    source,
+                    components::GeneratorType::Inspectors,
+                    destination,
+                    &options.to_options(&template_dirs),
                 ),
-                GeneratorTypes::Hpp => {
-                    components::generate(source, components::GeneratorType::HeaderFile, destination)
-                }
             },
+            GenerateCommands::All {
+                destination,
+                sources,
+                options,
+            } => {
+                components::generate_all(sources, destination, &options.to_options(&template_dirs))
+            }
+            GenerateCommands::Layout {
+                sources,
+                import_path,
+            } => components::check_layout(
+                sources,
+                &components::Options {
+                    import_path: import_path.clone(),
+                    ..Default::default()
+                },
+            ),
+            GenerateCommands::Schema {
+                destination,
+                sources,
+                import_path,
+                check,
+            } => components::generate_schema(
+                sources,
+                destination,
+                &components::Options {
+                    import_path: import_path.clone(),
+                    check: *check,
+                    ..Default::default()
+                },
+            ),
         },
-    }
+    };
 
     // Continued program logic goes here...
+
+    exit_on_errors(result);
+}
+
+/// Reports the problems a command found and exits with a non-zero code, if there were any
+fn exit_on_errors(result: Result<(), Vec<Diagnostic>>) {
+    if let Err(errors) = result {
+        report_all(&errors);
+        std::process::exit(1);
+    }
 }
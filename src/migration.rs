@@ -0,0 +1,703 @@
+use crate::components::model::{Component, FieldType, MigrationRule};
+use crate::components::toml_error_hint;
+use crate::content::{recorded_version, ContentType, BINDING_PREFIX};
+use crate::diagnostics::{Diagnostic, Diagnostics, Location};
+use crate::utils::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use toml_edit::{value, Decor, Document, InlineTable, Item, Table, TableLike, Value};
+
+/// Converts a content value to `data_type` for a change-type migration, keeping its
+/// formatting. Returns `None` if the value does not fit in the new type.
+fn convert(original: &Value, data_type: &FieldType) -> Option<Value> {
+    let mut converted = match data_type {
+        FieldType::Float | FieldType::Double => match original {
+            Value::Float(_) => return Some(original.clone()),
+            Value::Integer(integer) => Value::from(*integer.value() as f64),
+            Value::String(text) => Value::from(text.value().trim().parse::<f64>().ok()?),
+            _ => return None,
+        },
+        FieldType::Bool => match original {
+            Value::Boolean(_) => return Some(original.clone()),
+            Value::Integer(integer) if matches!(integer.value(), 0 | 1) => {
+                Value::from(*integer.value() == 1)
+            }
+            Value::String(text) => Value::from(text.value().trim().parse::<bool>().ok()?),
+            _ => return None,
+        },
+        FieldType::Ref | FieldType::Signal => match original {
+            Value::String(_) | Value::Integer(_) => return Some(original.clone()),
+            _ => return None,
+        },
+        FieldType::HashedString | FieldType::Resource(_) | FieldType::String(_) => match original {
+            Value::String(_) => return Some(original.clone()),
+            Value::Integer(integer) => Value::from(integer.value().to_string()),
+            Value::Float(float) => Value::from(float.value().to_string()),
+            Value::Boolean(boolean) => Value::from(boolean.value().to_string()),
+            _ => return None,
+        },
+        _ => {
+            let (minimum, maximum) = data_type.integer_range()?;
+            let integer = match original {
+                Value::Integer(integer) => *integer.value(),
+                // Only whole numbers, rather than silently rounding content
+                Value::Float(float) if float.value().fract() == 0.0 => *float.value() as i64,
+                Value::Boolean(boolean) => *boolean.value() as i64,
+                Value::String(text) => text.value().trim().parse::<i64>().ok()?,
+                _ => return None,
+            };
+            if (integer as i128) < minimum || (integer as i128) > maximum {
+                return None;
+            }
+            if original.is_integer() {
+                return Some(original.clone());
+            }
+            Value::from(integer)
+        }
+    };
+    *converted.decor_mut() = original.decor().clone();
+    Some(converted)
+}
+
+/// The column the values of `table` start at, if its keys are aligned by padding the
+/// shorter ones with spaces
+fn aligned_column(table: &dyn TableLike) -> Option<usize> {
+    let mut aligned = false;
+    let mut column = 0;
+    for (name, _) in table.iter() {
+        if let Some(suffix) = table.key_decor(name).and_then(Decor::suffix) {
+            if suffix.trim().is_empty() {
+                aligned |= suffix.len() > 1;
+                column = column.max(name.len() + suffix.len());
+            }
+        }
+    }
+    aligned.then_some(column)
+}
+
+/// Formatting for a new key `name`, placed like the key formatted with `decor`: indented
+/// the same, and aligned at `column` if the keys of the table are aligned. Only the
+/// `first` key replacing it keeps the comments above it.
+fn key_decor_like(decor: &Decor, name: &str, first: bool, column: Option<usize>) -> Decor {
+    let prefix = match decor.prefix() {
+        Some(prefix) if first => prefix,
+        Some(prefix) => prefix
+            .rsplit('\n')
+            .next()
+            .filter(|indentation| indentation.trim().is_empty())
+            .unwrap_or_default(),
+        None => return Decor::default(),
+    };
+    let suffix = match (decor.suffix(), column) {
+        (Some(suffix), Some(column)) if suffix.trim().is_empty() => {
+            " ".repeat(column.saturating_sub(name.len()).max(1))
+        }
+        (suffix, _) => suffix.unwrap_or_default().to_string(),
+    };
+    Decor::new(prefix, suffix)
+}
+
+/// Replaces the entry `key` of `table` by `entries`, in its place, keeping the formatting
+/// of every other entry
+fn replace_entry(table: &mut dyn TableLike, key: &str, entries: Vec<(String, Item)>) {
+    let column = aligned_column(table);
+    let existing: Vec<(String, Option<Decor>, Item)> = table
+        .iter()
+        .map(|(name, item)| {
+            (
+                name.to_string(),
+                table.key_decor(name).cloned(),
+                item.clone(),
+            )
+        })
+        .collect();
+    table.clear();
+    let mut entries = Some(entries);
+    for (name, decor, item) in existing {
+        if name != key {
+            table.insert(&name, item);
+            if let (Some(decor), Some(key_decor)) = (decor, table.key_decor_mut(&name)) {
+                *key_decor = decor;
+            }
+            continue;
+        }
+        for (index, (new_name, new_item)) in entries.take().unwrap().into_iter().enumerate() {
+            table.insert(&new_name, new_item);
+            if let (Some(decor), Some(key_decor)) = (&decor, table.key_decor_mut(&new_name)) {
+                *key_decor = key_decor_like(decor, &new_name, index == 0, column);
+            }
+        }
+    }
+}
+
+/// Adds the entry `key` to the end of `table`, formatted like the last entry
+fn append_entry(table: &mut dyn TableLike, key: &str, item: Item) {
+    let column = aligned_column(table);
+    let decor = table
+        .iter()
+        .last()
+        .and_then(|(name, _)| Some(key_decor_like(table.key_decor(name)?, key, false, column)));
+    table.insert(key, item);
+    if let (Some(decor), Some(key_decor)) = (decor, table.key_decor_mut(key)) {
+        *key_decor = decor;
+    }
+}
+
+/// Migrates the entities of one content file
+struct Migrator<'a> {
+    diagnostics: Diagnostics,
+    components: &'a HashMap<String, &'a Component>,
+    /// The version each component was recorded at before migrating
+    recorded: HashMap<String, i64>,
+    /// The versions of the components that were migrated, to record in the file
+    migrated: BTreeMap<String, u32>,
+}
+
+impl<'a> Migrator<'a> {
+    fn error(&mut self, location: Option<Location>, message: String, hint: Option<String>) {
+        self.diagnostics.error(location, message, hint);
+    }
+
+    /// Applies a migration rule to the table of a component called `name`
+    fn apply(
+        &mut self,
+        location: Option<Location>,
+        name: &str,
+        rule: &MigrationRule,
+        table: &mut dyn TableLike,
+    ) {
+        match rule {
+            MigrationRule::Rename { field, to } => {
+                let item = match table.get(field) {
+                    Some(item) => item.clone(),
+                    None => return,
+                };
+                if table.contains_key(to) {
+                    return self.error(
+                        location,
+                        format!("\"{}\" has both \"{}\" and \"{}\"", name, field, to),
+                        Some(format!(
+                            "\"{}\" was renamed to \"{}\", remove one of them",
+                            field, to
+                        )),
+                    );
+                }
+                replace_entry(table, field, vec![(to.clone(), item)]);
+            }
+            MigrationRule::ChangeType { field, to } => {
+                let original = match table.get(field).and_then(Item::as_value) {
+                    Some(Value::String(binding)) if binding.value().starts_with(BINDING_PREFIX) => {
+                        return
+                    }
+                    Some(original) => original.clone(),
+                    None => return,
+                };
+                match convert(&original, to) {
+                    Some(converted) => *table.get_mut(field).unwrap() = Item::Value(converted),
+                    None => self.error(
+                        location,
+                        format!(
+                            "cannot change {} of \"{}\" in \"{}\" to {}",
+                            original.to_string().trim(),
+                            field,
+                            name,
+                            to.name()
+                        ),
+                        Some(String::from(
+                            "the value does not fit in the new type, change it by hand",
+                        )),
+                    ),
+                }
+            }
+            MigrationRule::Split { field, into } => {
+                let original = match table.get(field) {
+                    Some(item) => item.as_value().cloned(),
+                    None => return,
+                };
+                let components: Option<Vec<Value>> = match &original {
+                    Some(Value::Array(array)) if array.len() == into.len() => {
+                        Some(array.iter().cloned().collect())
+                    }
+                    Some(Value::InlineTable(vector)) if vector.len() == into.len() => {
+                        let names = if vector.contains_key("r") {
+                            ["r", "g", "b", "a"]
+                        } else {
+                            ["x", "y", "z", "w"]
+                        };
+                        names[..into.len()]
+                            .iter()
+                            .map(|name| vector.get(name).cloned())
+                            .collect()
+                    }
+                    _ => None,
+                };
+                let components = match components {
+                    Some(components) => components,
+                    None => {
+                        return self.error(
+                            location,
+                            format!(
+                                "\"{}\" of \"{}\" must be a vector of {} numbers to be split",
+                                field,
+                                name,
+                                into.len()
+                            ),
+                            Some(format!("it is split into: {}", into.join(", "))),
+                        )
+                    }
+                };
+                if let Some(existing) = into.iter().find(|x| *x != field && table.contains_key(x)) {
+                    return self.error(
+                        location,
+                        format!("\"{}\" has both \"{}\" and \"{}\"", name, field, existing),
+                        Some(format!(
+                            "\"{}\" was split into {}, remove one of them",
+                            field,
+                            into.join(", ")
+                        )),
+                    );
+                }
+                let entries = into
+                    .iter()
+                    .zip(components)
+                    .map(|(name, mut component)| {
+                        component.decor_mut().clear();
+                        (name.clone(), Item::Value(component))
+                    })
+                    .collect();
+                replace_entry(table, field, entries);
+            }
+            MigrationRule::Add { field, value } => {
+                if !table.contains_key(field) {
+                    let mut value = value.clone();
+                    value.decor_mut().clear();
+                    append_entry(table, field, Item::Value(value));
+                }
+            }
+        }
+    }
+
+    /// Migrates every component of an entity table found at `location`
+    fn migrate_entity(
+        &mut self,
+        content_type: ContentType,
+        location: Option<Location>,
+        entity: &mut dyn TableLike,
+    ) {
+        let names: Vec<String> = entity.iter().map(|(name, _)| name.to_string()).collect();
+        for name in names {
+            if content_type == ContentType::Prop && name == "metadata" {
+                continue;
+            }
+            // Unknown components are left for check-content to report
+            let component = match self.components.get(&name) {
+                Some(component) => *component,
+                None => continue,
+            };
+            let component_location = match content_type {
+                ContentType::Prop => self.diagnostics.locator.table(&name),
+                ContentType::Scene => None,
+            }
+            .or_else(|| self.diagnostics.locator.key(location, &name))
+            .or(location);
+            let version = self.recorded.get(&name).copied().unwrap_or(1);
+            if version > component.version as i64 {
+                self.error(
+                    component_location,
+                    format!(
+                        "\"{}\" was written for version {} of the component, but the latest is version {}",
+                        name, version, component.version
+                    ),
+                    Some(String::from(
+                        "the file is newer than the components.toml files it is migrated with",
+                    )),
+                );
+                continue;
+            }
+            let table = match entity.get_mut(&name).and_then(Item::as_table_like_mut) {
+                Some(table) => table,
+                None => continue,
+            };
+            for migration in &component.migrations {
+                if migration.version as i64 > version {
+                    self.apply(component_location, &name, &migration.rule, table);
+                }
+            }
+            if version < component.version as i64 {
+                self.migrated.insert(name, component.version);
+            }
+        }
+    }
+}
+
+/// Records the versions the components of a content file were migrated to in its
+/// `[metadata.versions]`
+fn record_versions(document: &mut Document, versions: &BTreeMap<String, u32>) {
+    if !document.contains_key("metadata") {
+        let mut metadata = Table::new();
+        metadata.set_implicit(true);
+        document.insert("metadata", Item::Table(metadata));
+    }
+    let metadata = &mut document["metadata"];
+    if metadata.get("versions").is_none() {
+        metadata["versions"] = if metadata.is_table() {
+            Item::Table(Table::new())
+        } else {
+            value(InlineTable::new())
+        };
+    }
+    for (name, version) in versions {
+        metadata["versions"][name.as_str()] = value(*version as i64);
+    }
+}
+
+/// Migrates the `source` of a scene or prop file read from `path` to the latest versions
+/// in `components`, keeping its formatting and comments. Returns `None` if it is already
+/// at the latest versions.
+fn migrate_source(
+    path: &str,
+    source: &str,
+    content_type: ContentType,
+    components: &HashMap<String, &Component>,
+) -> Result<Option<String>, Vec<Diagnostic>> {
+    let mut diagnostics = Diagnostics::new(path, source);
+    let mut document = match source.parse::<Document>() {
+        Ok(document) => document,
+        Err(error) => {
+            diagnostics.error(
+                error
+                    .line_col()
+                    .map(|(line, column)| Location { line, column }),
+                String::from("content file is not valid TOML"),
+                toml_error_hint(&error),
+            );
+            return Err(diagnostics.errors);
+        }
+    };
+    let mut migrator = Migrator {
+        diagnostics,
+        components,
+        recorded: components
+            .keys()
+            .map(|name| (name.clone(), recorded_version(document.as_table(), name)))
+            .collect(),
+        migrated: BTreeMap::new(),
+    };
+
+    match content_type {
+        ContentType::Prop => migrator.migrate_entity(content_type, None, document.as_table_mut()),
+        ContentType::Scene => {
+            if let Some(entities) = document
+                .get_mut("entity")
+                .and_then(Item::as_array_of_tables_mut)
+            {
+                for (index, entity) in entities.iter_mut().enumerate() {
+                    let location = migrator.diagnostics.locator.array_table("entity", index);
+                    migrator.migrate_entity(content_type, Some(location), entity);
+                }
+            }
+        }
+    }
+
+    if migrator.diagnostics.has_errors() {
+        return Err(migrator.diagnostics.errors);
+    }
+    if migrator.migrated.is_empty() {
+        return Ok(None);
+    }
+    record_versions(&mut document, &migrator.migrated);
+    Ok(Some(document.to_string()))
+}
+
+/// Migrates the components of a scene or prop file to the latest versions in `components`,
+/// rewriting the file in place and keeping its formatting and comments. Returns the
+/// problems found instead, leaving the file untouched, if there are any.
+pub fn migrate(
+    path: &str,
+    content_type: ContentType,
+    components: &HashMap<String, &Component>,
+) -> Result<FileStatus, Vec<Diagnostic>> {
+    let source = fs::read_to_string(path).unwrap_or_default();
+    match migrate_source(path, &source, content_type, components)? {
+        Some(migrated) => update_file_with_str(path, &migrated).map_err(|error| vec![error]),
+        None => Ok(FileStatus::Unchanged),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::model::{self, ComponentsFile};
+    use crate::content::component_names;
+    use std::sync::Arc;
+
+    /// A component at version 3, with a migration of each kind
+    const COMPONENTS: &str = r#"
+namespace = "test"
+
+[[component]]
+    _name_       = "mover"
+    _version_    = 3
+    speed        = "float"
+    count        = "uint8"
+    width        = "float"
+    height       = "float"
+    mass         = "float"
+    _migrations_ = [
+        { version = 2, rename = "velocity", to = "speed" },
+        { version = 2, change-type = "count", to = "uint8" },
+        { version = 3, split = "dimensions", into = ["width", "height"] },
+        { version = 3, add = "mass", value = 1.0 },
+    ]
+"#;
+
+    fn components() -> Arc<ComponentsFile> {
+        let mut diagnostics = Diagnostics::new("components.toml", COMPONENTS);
+        let defs = COMPONENTS.parse::<Document>().unwrap();
+        let file = model::parse(&mut diagnostics, &defs, &[]);
+        assert!(diagnostics.errors.is_empty());
+        Arc::new(file.unwrap())
+    }
+
+    /// Migrates the content `source`, returning `None` if it is already up to date
+    fn migrate_str(
+        content_type: ContentType,
+        source: &str,
+    ) -> Result<Option<String>, Vec<Diagnostic>> {
+        let files = [components()];
+        migrate_source(
+            "content.toml",
+            source,
+            content_type,
+            &component_names(&files),
+        )
+    }
+
+    /// Where each error was found, and its message
+    fn errors(errors: &[Diagnostic]) -> Vec<(Location, &str)> {
+        errors
+            .iter()
+            .map(|error| (error.location.unwrap(), error.message.as_str()))
+            .collect()
+    }
+
+    fn at(line: usize, column: usize) -> Location {
+        Location { line, column }
+    }
+
+    /// Converts the `value` of `source`, as it was parsed with its formatting
+    fn converted(source: &str, data_type: FieldType) -> Option<String> {
+        let document = source.parse::<Document>().unwrap();
+        convert(document["value"].as_value().unwrap(), &data_type).map(|value| value.to_string())
+    }
+
+    #[test]
+    fn convert_keeps_formatting() {
+        assert_eq!(
+            converted("value = 3 # three", FieldType::Float).as_deref(),
+            Some(" 3.0 # three")
+        );
+        assert_eq!(
+            converted("value = \"4\"  ", FieldType::UInt8).as_deref(),
+            Some(" 4  ")
+        );
+        assert_eq!(
+            converted("value = 1", FieldType::Bool).as_deref(),
+            Some(" true")
+        );
+        assert_eq!(
+            converted("value = 2.0", FieldType::Int32).as_deref(),
+            Some(" 2")
+        );
+        assert_eq!(
+            converted("value = 0x10", FieldType::Int32).as_deref(),
+            Some(" 0x10")
+        );
+        assert_eq!(
+            converted("value = 1.5", FieldType::HashedString).as_deref(),
+            Some(" \"1.5\"")
+        );
+    }
+
+    #[test]
+    fn convert_rejects_values_out_of_range() {
+        assert_eq!(converted("value = 256", FieldType::UInt8), None);
+        assert_eq!(converted("value = -1", FieldType::UInt32), None);
+        assert_eq!(converted("value = -129", FieldType::Int8), None);
+        assert_eq!(converted("value = 1.5", FieldType::Int32), None);
+        assert_eq!(converted("value = 2", FieldType::Bool), None);
+        assert_eq!(converted("value = \"many\"", FieldType::Float), None);
+        assert_eq!(
+            converted("value = 255", FieldType::UInt8).as_deref(),
+            Some(" 255")
+        );
+    }
+
+    #[test]
+    fn migrates_a_prop_keeping_comments_and_alignment() {
+        let prop = r#"# The player
+[mover]
+    # how fast it goes
+    velocity   = 2.5 # units per second
+    count      = "3"
+    # width and height
+    dimensions = [1.0, 2.0]
+
+[other]
+    velocity = 1
+"#;
+        let migrated = r#"# The player
+[mover]
+    # how fast it goes
+    speed      = 2.5 # units per second
+    count      = 3
+    # width and height
+    width      = 1.0
+    height     = 2.0
+    mass       = 1.0
+
+[other]
+    velocity = 1
+
+[metadata.versions]
+mover = 3
+"#;
+        assert_eq!(
+            migrate_str(ContentType::Prop, prop).unwrap().unwrap(),
+            migrated
+        );
+    }
+
+    #[test]
+    fn replaces_unaligned_keys_with_single_spaces() {
+        let prop = "[mover]\n  dimensions = [1.0, 2.0] # size\n  count = 1\n";
+        let migrated = "[mover]\n  width = 1.0\n  height = 2.0\n  count = 1\n  mass = 1.0\n\n\
+                        [metadata.versions]\nmover = 3\n";
+        assert_eq!(
+            migrate_str(ContentType::Prop, prop).unwrap().unwrap(),
+            migrated
+        );
+    }
+
+    #[test]
+    fn splits_inline_tables_and_arrays() {
+        let scene = r#"[[entity]]
+    mover = { dimensions = { x = 3.0, y = 4.0 } }
+
+[[entity]]
+    mover = { dimensions = [5, 6], mass = 2.0 }
+
+[[entity]]
+    mover.dimensions = { r = 0.5, g = 0.25 }
+"#;
+        let migrated = r#"[[entity]]
+    mover = { width = 3.0, height = 4.0, mass = 1.0 }
+
+[[entity]]
+    mover = { width = 5, height = 6, mass = 2.0 }
+
+[[entity]]
+    mover.width = 0.5
+    mover.height = 0.25
+    mover.mass = 1.0
+
+[metadata.versions]
+mover = 3
+"#;
+        assert_eq!(
+            migrate_str(ContentType::Scene, scene).unwrap().unwrap(),
+            migrated
+        );
+    }
+
+    #[test]
+    fn reports_vectors_that_cannot_be_split() {
+        let scene = "[[entity]]\n    mover = { dimensions = [1.0, 2.0, 3.0] }\n";
+        let found = migrate_str(ContentType::Scene, scene).unwrap_err();
+        assert_eq!(
+            errors(&found),
+            [(
+                at(1, 4),
+                "\"dimensions\" of \"mover\" must be a vector of 2 numbers to be split"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_values_that_do_not_fit_where_they_are() {
+        let scene = r#"[[entity]]
+    mover = { count = 300 }
+
+[[entity]]
+    named.name = "still"
+
+[[entity]]
+    mover = { count = 1.5 }
+"#;
+        let found = migrate_str(ContentType::Scene, scene).unwrap_err();
+        assert_eq!(
+            errors(&found),
+            [
+                (
+                    at(1, 4),
+                    "cannot change 300 of \"count\" in \"mover\" to uint8"
+                ),
+                (
+                    at(7, 4),
+                    "cannot change 1.5 of \"count\" in \"mover\" to uint8"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn records_the_migrated_versions() {
+        // Versions of an inline metadata table are kept inline
+        let scene = r#"[[entity]]
+    mover = { speed = 1.0, mass = 1.0 }
+
+[metadata]
+    versions = { other = 2, mover = 2 }
+"#;
+        let migrated = scene.replace("mover = 2", "mover = 3");
+        assert_eq!(
+            migrate_str(ContentType::Scene, scene).unwrap().unwrap(),
+            migrated
+        );
+        // Props get a versions table next to the rest of their metadata
+        let prop = r#"[metadata.info]
+    name = "Mover"
+
+[mover]
+    speed = 1.0
+"#;
+        let migrated = r#"[metadata.info]
+    name = "Mover"
+
+[metadata.versions]
+mover = 3
+
+[mover]
+    speed = 1.0
+    mass = 1.0
+"#;
+        assert_eq!(
+            migrate_str(ContentType::Prop, prop).unwrap().unwrap(),
+            migrated
+        );
+    }
+
+    #[test]
+    fn leaves_content_at_the_latest_version_unchanged() {
+        let prop = "[metadata.versions]\n    mover = 3\n\n[mover]\n    velocity = 1.0\n";
+        assert_eq!(migrate_str(ContentType::Prop, prop).unwrap(), None);
+        let found = migrate_str(ContentType::Prop, &prop.replace('3', "4")).unwrap_err();
+        assert_eq!(
+            errors(&found),
+            [(
+                at(3, 0),
+                "\"mover\" was written for version 4 of the component, but the latest is version 3"
+            )]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::components;
+use crate::diagnostics::Diagnostic;
 use crate::utils::*;
 
 fn get_base_path(module: &str, project: &Option<String>) -> String {
@@ -8,31 +8,39 @@ fn get_base_path(module: &str, project: &Option<String>) -> String {
     }
 }
 
-pub fn new(module: &str, project: &Option<String>) {
+pub fn new(
+    module: &str,
+    project: &Option<String>,
+    template_dirs: &[String],
+) -> Result<(), Diagnostic> {
     let base_path = get_base_path(module, project);
     // Create module directory
-    make_directory(&base_path);
+    make_directory(&base_path)?;
 
     // Populate module with default files
     make_file(
         &format!("{}/components.toml", base_path),
         "module/components.toml",
         &liquid::object!({ "module_name": module }),
-    );
+        template_dirs,
+    )?;
     make_file(
         &format!("{}/main.cpp", base_path),
         "module/main.cpp",
         &liquid::object!({}),
-    );
+        template_dirs,
+    )?;
     make_file(
         &format!("{}/CMakeLists.txt", base_path),
         "module/CMakeLists.txt",
         &liquid::object!({}),
-    );
+        template_dirs,
+    )?;
+    Ok(())
 }
 
 pub fn build(module: &str, project: &Option<String>) {
-    let base_path = get_base_path(module, project);
+    let _base_path = get_base_path(module, project);
     // components::generate(&base_path, true, true);
     println!(
         "NOT IMPLEMENTED: module {} build (project={})",
@@ -1,10 +1,17 @@
+use crate::components::{self, model::ComponentsFile, toml_error_hint};
+use crate::content::{self, ContentType};
+use crate::diagnostics::{Diagnostic, Diagnostics, Location};
 use crate::events;
+use crate::migration;
 use crate::utils::*;
 use clap::ArgEnum;
 use colored::*;
+use std::path::Path;
 use std::process::Command;
+use std::sync::Arc;
 use std::{env, fs, io};
 use strum_macros::Display;
+use toml_edit::Document;
 
 #[derive(Display, Copy, Clone, ArgEnum)]
 pub enum RunBuild {
@@ -23,68 +30,78 @@ pub enum LogLevel {
     Trace,
 }
 
-pub fn create(project: &str) {
+pub fn create(project: &str, template_dirs: &[String]) -> Result<(), Diagnostic> {
     let project_dir = get_project_dir(project);
 
     println!("Creating: {}", project_dir.blue());
     // Create directory structure
     for dir in ["scenes", "props", "features"] {
         // println!("Creating: projects/{}/{}", name.blue(), dir.blue());
-        make_directory(&format!("{}/{}", project_dir, dir));
+        make_directory(&format!("{}/{}", project_dir, dir))?;
     }
     make_file(
         &format!("{}/.gitignore", project_dir),
         "project/gitignore",
         &liquid::object!({}),
-    );
+        template_dirs,
+    )?;
     // Game configuration
     make_file(
         &format!("{}/config.toml", project_dir),
         "project/config.toml",
         &liquid::object!({ "project_name": project }),
-    );
+        template_dirs,
+    )?;
     make_file(
         &format!("{}/game.toml", project_dir),
         "project/game.toml",
         &liquid::object!({}),
-    );
+        template_dirs,
+    )?;
     make_file(
         &format!("{}/events.toml", project_dir),
         "project/events.toml",
         &liquid::object!({}),
-    );
+        template_dirs,
+    )?;
     // Default scene
     make_file(
         &format!("{}/scenes/default.toml", project_dir),
         "project/default_scene.toml",
         &liquid::object!({}),
-    );
+        template_dirs,
+    )?;
     // Player prop
-    make_directory(&format!("{}/props/player/assets", project_dir));
-    make_directory(&format!("{}/props/player/scripts", project_dir));
+    make_directory(&format!("{}/props/player/assets", project_dir))?;
+    make_directory(&format!("{}/props/player/scripts", project_dir))?;
     make_file(
         &format!("{}/props/player/entity.toml", project_dir),
         "project/player_template.toml",
         &liquid::object!({}),
-    );
+        template_dirs,
+    )?;
     make_file(
         &format!("{}/props/player/scripts/player.lua", project_dir),
         "project/player_script.lua",
         &liquid::object!({}),
-    );
+        template_dirs,
+    )?;
     // Game setup "feature"
-    make_directory(&format!("{}/features/game/assets", project_dir));
-    make_directory(&format!("{}/features/game/scripts", project_dir));
+    make_directory(&format!("{}/features/game/assets", project_dir))?;
+    make_directory(&format!("{}/features/game/scripts", project_dir))?;
     make_file(
         &format!("{}/features/game/config.toml", project_dir),
         "project/feature_config.toml",
         &liquid::object!({}),
-    );
+        template_dirs,
+    )?;
     make_file(
         &format!("{}/features/game/scripts/run.lua", project_dir),
         "project/feature_script.lua",
         &liquid::object!({}),
-    );
+        template_dirs,
+    )?;
+    Ok(())
 }
 
 fn _is_root_dir() -> io::Result<bool> {
@@ -97,6 +114,14 @@ fn is_root_dir() -> bool {
     _is_root_dir().unwrap_or(false)
 }
 
+fn get_root_dir() -> &'static str {
+    if is_root_dir() {
+        "."
+    } else {
+        "../.."
+    }
+}
+
 fn get_project_dir(project: &str) -> String {
     if is_root_dir() {
         format!("projects/{}", project)
@@ -105,27 +130,38 @@ fn get_project_dir(project: &str) -> String {
     }
 }
 
-pub fn build(project: &str, builddir: &Option<String>) {
+/// Directory of a project's templates, which replace the embedded ones of the same name
+pub fn templates_dir(project: &str) -> String {
+    format!("{}/templates", get_project_dir(project))
+}
+
+pub fn build(
+    project: &str,
+    builddir: &Option<String>,
+    template_dirs: &[String],
+) -> Result<(), Vec<Diagnostic>> {
     let project_dir = get_project_dir(project);
     let output_dir: String = match builddir {
         Some(dir) => dir.to_string(),
         None => format!("{}/build", project_dir),
     };
-    make_directory(&format!("{}", output_dir));
+    make_directory(&output_dir).map_err(|error| vec![error])?;
     events::generate(
         format!("{}/events.toml", project_dir).as_str(),
         true,
         false,
         &output_dir,
-    );
+        template_dirs,
+    )?;
     println!("WIP: project {} build", project);
+    Ok(())
 }
 
-pub fn dev(project: &str) {}
+pub fn dev(_project: &str) {}
 
 fn _run(root_dir: &str, project_dir: &str, binary: &str, log_level: &LogLevel) -> io::Result<bool> {
     println!("Running {}", binary.blue());
-    let output = Command::new(binary)
+    let _output = Command::new(binary)
         .args(["-g", &format!("{}/engine/src/lua", root_dir)])
         .args(["-g", project_dir])
         .args(["--init", &format!("{}/config.toml", project_dir)])
@@ -176,3 +212,198 @@ pub fn run(project: &str, engine_build: &RunBuild, log_level: &Option<LogLevel>)
 pub fn release(project: &str) {
     println!("NOT IMPLEMENTED: project {} release", project);
 }
+
+/// Directories of the modules enabled in the project's game.toml. Module paths are
+/// looked up in the project first, then in the root directory.
+fn enabled_modules(project_dir: &str) -> Result<Vec<String>, Vec<Diagnostic>> {
+    let path = format!("{}/game.toml", project_dir);
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(error) => {
+            let mut diagnostics = Diagnostics::new(&path, "");
+            diagnostics.error(None, format!("could not read game.toml: {}", error), None);
+            return Err(diagnostics.errors);
+        }
+    };
+    let mut diagnostics = Diagnostics::new(&path, &source);
+    let document = match source.parse::<Document>() {
+        Ok(document) => document,
+        Err(error) => {
+            diagnostics.error(
+                error
+                    .line_col()
+                    .map(|(line, column)| Location { line, column }),
+                String::from("game.toml is not valid TOML"),
+                toml_error_hint(&error),
+            );
+            return Err(diagnostics.errors);
+        }
+    };
+    let mut modules = Vec::new();
+    if let Some(tables) = document.get("module").and_then(|x| x.as_array_of_tables()) {
+        for (index, module) in tables.iter().enumerate() {
+            if module.get("enabled").and_then(|x| x.as_bool()) == Some(false) {
+                continue;
+            }
+            let name = module.get("name").and_then(|x| x.as_str()).unwrap_or("");
+            let module_path = module.get("path").and_then(|x| x.as_str()).unwrap_or("");
+            let directory = [project_dir, get_root_dir()]
+                .iter()
+                .map(|base| format!("{}/{}/{}", base, module_path.trim_end_matches('/'), name))
+                .find(|directory| Path::new(directory).is_dir());
+            match directory {
+                Some(directory) => modules.push(directory),
+                None => {
+                    let location = diagnostics.locator.array_table("module", index);
+                    diagnostics.error(
+                        Some(location),
+                        format!("module \"{}\" was not found", name),
+                        Some(format!(
+                            "looked in {0}/{1} and {2}/{1}",
+                            project_dir,
+                            module_path.trim_end_matches('/'),
+                            get_root_dir()
+                        )),
+                    );
+                }
+            }
+        }
+    }
+    if diagnostics.has_errors() {
+        Err(diagnostics.errors)
+    } else {
+        Ok(modules)
+    }
+}
+
+/// Files in `directory` for which `path` returns a content file, sorted by name
+fn content_files(directory: &str, path: impl Fn(&Path) -> Option<String>) -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(directory)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| path(&entry.path()))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// The components a project uses, and its content files with their types
+type ProjectContent = (Vec<Arc<ComponentsFile>>, Vec<(String, ContentType)>);
+
+/// The components of the engine core and of the enabled modules, and the project's
+/// scenes and prop templates, which use them. Returns the problems found instead if the
+/// components have errors.
+fn project_content(project_dir: &str) -> Result<ProjectContent, Vec<Diagnostic>> {
+    let mut sources = vec![format!("{}/engine", get_root_dir())];
+    sources.extend(enabled_modules(project_dir)?);
+    let definitions: Vec<Arc<ComponentsFile>> =
+        components::load_all(&sources, &components::Options::default())?
+            .into_iter()
+            .map(|(_, components)| components)
+            .collect();
+
+    let scenes = content_files(&format!("{}/scenes", project_dir), |path| {
+        path.extension()
+            .is_some_and(|x| x == "toml")
+            .then(|| path.to_string_lossy().to_string())
+    });
+    let props = content_files(&format!("{}/props", project_dir), |path| {
+        let entity = path.join("entity.toml");
+        entity
+            .is_file()
+            .then(|| entity.to_string_lossy().to_string())
+    });
+    let files = scenes
+        .into_iter()
+        .map(|file| (file, ContentType::Scene))
+        .chain(props.into_iter().map(|file| (file, ContentType::Prop)))
+        .collect();
+    Ok((definitions, files))
+}
+
+/// A summary of the content files that had errors, after their own problems
+fn failed_files(project_dir: &str, message: String, errors: &mut Vec<Diagnostic>) {
+    let mut diagnostics = Diagnostics::new(project_dir, "");
+    diagnostics.error(None, message, None);
+    errors.append(&mut diagnostics.errors);
+}
+
+/// Checks the project's scenes and prop templates against the components of the
+/// engine core and of the enabled modules
+pub fn check_content(project: &str) -> Result<(), Vec<Diagnostic>> {
+    let project_dir = get_project_dir(project);
+    let (definitions, files) = project_content(&project_dir)?;
+    let names = content::component_names(&definitions);
+
+    let mut errors = Vec::new();
+    let mut failed = 0;
+    for (file, content_type) in &files {
+        if let Err(mut file_errors) = content::check(file, *content_type, &names) {
+            errors.append(&mut file_errors);
+            failed += 1;
+        }
+    }
+    let checked = files.len();
+    if failed > 0 {
+        failed_files(
+            &project_dir,
+            format!("{} of {} content files have errors", failed, checked),
+            &mut errors,
+        );
+        return Err(errors);
+    }
+    println!(
+        "Checked {} content file{}: {}",
+        checked,
+        if checked == 1 { "" } else { "s" },
+        "no errors".green()
+    );
+    Ok(())
+}
+
+/// Migrates the project's scenes and prop templates to the latest versions of the
+/// components of the engine core and of the enabled modules
+pub fn migrate(project: &str) -> Result<(), Vec<Diagnostic>> {
+    let project_dir = get_project_dir(project);
+    let (definitions, files) = project_content(&project_dir)?;
+    let names = content::component_names(&definitions);
+
+    let mut errors = Vec::new();
+    let mut migrated = 0;
+    let mut failed = 0;
+    for (file, content_type) in &files {
+        match migration::migrate(file, *content_type, &names) {
+            Ok(FileStatus::Unchanged) => (),
+            Ok(_) => {
+                println!("  {} {}", "migrated".green(), file);
+                migrated += 1;
+            }
+            Err(mut file_errors) => {
+                errors.append(&mut file_errors);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        failed_files(
+            &project_dir,
+            format!(
+                "{} of {} content files could not be migrated, and were left unchanged",
+                failed,
+                files.len()
+            ),
+            &mut errors,
+        );
+        return Err(errors);
+    }
+    println!(
+        "Migrated {} of {} content file{}",
+        migrated,
+        files.len(),
+        if files.len() == 1 { "" } else { "s" }
+    );
+    Ok(())
+}
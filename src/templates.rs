@@ -0,0 +1,56 @@
+use crate::components::model::suggest_name;
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::utils::*;
+use colored::*;
+use std::path::Path;
+
+/// Prints the name of every template, and the file in `template_dirs` overriding it if
+/// there is one
+pub fn list(template_dirs: &[String]) {
+    for name in template_names() {
+        match template_override(&name, template_dirs) {
+            Some(path) => println!("{} ({} {})", name, "overridden by".dimmed(), path.blue()),
+            None => println!("{}", name),
+        }
+    }
+}
+
+/// Copies the embedded template called `name` into `templates_dir`, where it overrides the
+/// embedded one and can be customised. Existing files are only replaced if `force` is set.
+pub fn eject(name: &str, templates_dir: &str, force: bool) -> Result<(), Vec<Diagnostic>> {
+    let path = output_path(templates_dir, name);
+    let mut diagnostics = Diagnostics::new(&path, "");
+    let contents = embedded_template(name);
+    match &contents {
+        None => {
+            let names = template_names();
+            diagnostics.error(
+                None,
+                format!("there is no template called \"{}\"", name),
+                Some(suggest_name(
+                    name,
+                    names.iter().map(String::as_str).collect(),
+                    "templates",
+                )),
+            );
+        }
+        Some(_) if !force && Path::new(&path).exists() => diagnostics.error(
+            None,
+            String::from("template has already been ejected"),
+            Some(String::from(
+                "pass --force to replace it with the embedded template",
+            )),
+        ),
+        Some(_) => (),
+    }
+    if diagnostics.has_errors() {
+        return Err(diagnostics.errors);
+    }
+
+    if let Some(parent) = Path::new(&path).parent() {
+        make_directory(&parent.to_string_lossy()).map_err(|error| vec![error])?;
+    }
+    println!("Ejecting {} to: {}", name, path.blue());
+    update_file_with_str(&path, &contents.unwrap()).map_err(|error| vec![error])?;
+    Ok(())
+}
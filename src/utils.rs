@@ -1,40 +1,266 @@
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use colored::*;
 use rust_embed::RustEmbed;
-use std::fs::{DirBuilder, File};
-use std::io::prelude::*;
+use similar::TextDiff;
+use std::fs::{self, DirBuilder};
+use std::path::Path;
 
 #[derive(RustEmbed)]
 #[folder = "templates/"]
 struct Templates;
 
-fn template(filename: &str) -> liquid::Template {
-    let file = Templates::get(&filename).unwrap();
-    let content = std::str::from_utf8(file.data.as_ref());
-    let template = liquid::ParserBuilder::with_stdlib()
-        .build()
-        .unwrap()
-        .parse(content.unwrap())
-        .unwrap();
-    template
+/// The template directories of a project: its `templates` directory, then the user's
+pub fn template_dirs(project_templates: &str) -> Vec<String> {
+    let mut dirs = vec![project_templates.to_string()];
+    dirs.extend(user_templates_dir());
+    dirs
 }
 
-pub fn template_to_str(template_file: &str, template_data: &liquid::Object) -> String {
-    template(template_file).render(template_data).unwrap()
+/// Directory in the user's configuration directory that templates are looked up in
+pub fn user_templates_dir() -> Option<String> {
+    dirs::config_dir().map(|dir| {
+        dir.join("million-monkeys")
+            .join("templates")
+            .to_string_lossy()
+            .to_string()
+    })
 }
 
-pub fn make_file(output_file: &str, template_file: &str, template_data: &liquid::Object) {
-    let config = template_to_str(template_file, template_data);
-    let mut file = File::create(output_file).unwrap();
-    file.write_all(config.as_bytes()).unwrap();
+/// Names of the embedded templates, in alphabetical order
+pub fn template_names() -> Vec<String> {
+    let mut names: Vec<String> = Templates::iter().map(|name| name.to_string()).collect();
+    names.sort();
+    names
 }
 
-pub fn make_file_with_str(output_file: &str, contents: &str) {
-    let mut file = File::create(output_file).unwrap();
-    file.write_all(contents.as_bytes()).unwrap();
+/// Contents of the embedded template called `name`, ignoring any override
+pub fn embedded_template(name: &str) -> Option<String> {
+    Templates::get(name).map(|file| String::from_utf8_lossy(file.data.as_ref()).to_string())
 }
 
-pub fn make_directory(directory_path: &str) {
+/// Path of the file that overrides the template called `name`, if there is one. The template
+/// is looked up by its name inside of each of `template_dirs` in turn, eg
+/// `<dir>/project/events.lua`.
+pub fn template_override(name: &str, template_dirs: &[String]) -> Option<String> {
+    template_dirs
+        .iter()
+        .map(|dir| output_path(dir, name))
+        .find(|path| Path::new(path).is_file())
+}
+
+/// A problem with the template at `path`, for overriding templates the hint suggests
+/// going back to the embedded one
+fn template_error(path: &str, content: &str, message: String, overridden: bool) -> Diagnostic {
+    let mut diagnostics = Diagnostics::new(path, content);
+    diagnostics.error(
+        None,
+        message,
+        overridden.then(|| String::from("fix or delete the file to use the built in template")),
+    );
+    diagnostics.errors.pop().unwrap()
+}
+
+fn template(filename: &str, template_dirs: &[String]) -> Result<liquid::Template, Diagnostic> {
+    let parser = liquid::ParserBuilder::with_stdlib().build().unwrap();
+    match template_override(filename, template_dirs) {
+        Some(path) => {
+            let content = fs::read_to_string(&path).map_err(|error| {
+                template_error(
+                    &path,
+                    "",
+                    format!("could not read template: {}", error),
+                    true,
+                )
+            })?;
+            parser.parse(&content).map_err(|error| {
+                template_error(
+                    &path,
+                    &content,
+                    format!("template is not valid: {}", error),
+                    true,
+                )
+            })
+        }
+        None => {
+            let file = Templates::get(filename).unwrap();
+            let content = String::from_utf8_lossy(file.data.as_ref());
+            parser.parse(&content).map_err(|error| {
+                template_error(
+                    filename,
+                    &content,
+                    format!("template is not valid: {}", error),
+                    false,
+                )
+            })
+        }
+    }
+}
+
+/// Renders the template called `template_file`, using the file overriding it in
+/// `template_dirs` if there is one
+pub fn template_to_str(
+    template_file: &str,
+    template_data: &liquid::Object,
+    template_dirs: &[String],
+) -> Result<String, Diagnostic> {
+    template(template_file, template_dirs)?
+        .render(template_data)
+        .map_err(|error| {
+            let (path, overridden) = match template_override(template_file, template_dirs) {
+                Some(path) => (path, true),
+                None => (template_file.to_string(), false),
+            };
+            template_error(
+                &path,
+                "",
+                format!("could not render template: {}", error),
+                overridden,
+            )
+        })
+}
+
+/// Renders a template to `output_file`, leaving the file untouched if it already holds the result
+pub fn make_file(
+    output_file: &str,
+    template_file: &str,
+    template_data: &liquid::Object,
+    template_dirs: &[String],
+) -> Result<FileStatus, Diagnostic> {
+    let config = template_to_str(template_file, template_data, template_dirs)?;
+    update_file_with_str(output_file, &config)
+}
+
+/// A problem with a file or directory that could not be read or written
+fn io_error(path: &str, message: String) -> Diagnostic {
+    let mut diagnostics = Diagnostics::new(path, "");
+    diagnostics.error(None, message, None);
+    diagnostics.errors.pop().unwrap()
+}
+
+fn write_file(output_file: &str, contents: &str) -> Result<(), Diagnostic> {
+    fs::write(output_file, contents)
+        .map_err(|error| io_error(output_file, format!("could not write file: {}", error)))
+}
+
+/// A file produced by a generator, named relative to the directory it belongs in
+#[derive(Clone, Debug)]
+pub struct GeneratedFile {
+    pub name: String,
+    pub contents: String,
+}
+
+/// What a generator produced, without anything having been written or printed.
+/// If there are diagnostics, no files are generated.
+#[derive(Clone, Debug, Default)]
+pub struct Generated {
+    pub files: Vec<GeneratedFile>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Generated {
+    pub fn new(files: Vec<GeneratedFile>) -> Generated {
+        Generated {
+            files,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn failed(diagnostics: Vec<Diagnostic>) -> Generated {
+        Generated {
+            files: Vec::new(),
+            diagnostics,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Contents of the generated file called `name`
+    pub fn file(&self, name: &str) -> Option<&str> {
+        self.files
+            .iter()
+            .find(|file| file.name == name)
+            .map(|file| file.contents.as_str())
+    }
+}
+
+/// A generator that stopped at a template problem produces no files
+impl From<Result<Vec<GeneratedFile>, Diagnostic>> for Generated {
+    fn from(result: Result<Vec<GeneratedFile>, Diagnostic>) -> Generated {
+        match result {
+            Ok(files) => Generated::new(files),
+            Err(error) => Generated::failed(vec![error]),
+        }
+    }
+}
+
+/// Path of the generated file `name` inside of `output_dir`
+pub fn output_path(output_dir: &str, name: &str) -> String {
+    format!("{}/{}", output_dir.trim_end_matches('/'), name)
+}
+
+/// What writing a generated file did to it
+#[derive(Clone, Copy, PartialEq)]
+pub enum FileStatus {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// Writes `contents` to `output_file`, unless the file already holds exactly that, so that
+/// build tools don't see it as modified
+pub fn update_file_with_str(output_file: &str, contents: &str) -> Result<FileStatus, Diagnostic> {
+    match fs::read_to_string(output_file) {
+        Ok(existing) if existing == contents => Ok(FileStatus::Unchanged),
+        existing => {
+            write_file(output_file, contents)?;
+            if existing.is_ok() {
+                Ok(FileStatus::Updated)
+            } else {
+                Ok(FileStatus::Created)
+            }
+        }
+    }
+}
+
+/// Compares `contents` with `output_file` without writing it, printing a diff of the
+/// lines that writing it would change
+pub fn check_file_with_str(output_file: &str, contents: &str) -> FileStatus {
+    match fs::read_to_string(output_file) {
+        Ok(existing) if existing == contents => FileStatus::Unchanged,
+        Ok(existing) => {
+            let diff = TextDiff::from_lines(existing.as_str(), contents)
+                .unified_diff()
+                .header(output_file, output_file)
+                .to_string();
+            for line in diff.lines() {
+                if line.starts_with("+++") || line.starts_with("---") {
+                    println!("{}", line.bold());
+                } else if line.starts_with('+') {
+                    println!("{}", line.green());
+                } else if line.starts_with('-') {
+                    println!("{}", line.red());
+                } else if line.starts_with("@@") {
+                    println!("{}", line.cyan());
+                } else {
+                    println!("{}", line);
+                }
+            }
+            FileStatus::Updated
+        }
+        Err(_) => FileStatus::Created,
+    }
+}
+
+pub fn make_directory(directory_path: &str) -> Result<(), Diagnostic> {
     DirBuilder::new()
         .recursive(true)
         .create(directory_path)
-        .unwrap();
+        .map_err(|error| {
+            io_error(
+                directory_path,
+                format!("could not create directory: {}", error),
+            )
+        })
 }
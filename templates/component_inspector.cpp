@@ -0,0 +1,6 @@
+    // components::{{namespace}}::{{class_name}}
+    void inspect_{{id}} (million::api::EngineSetup* engine, entt::registry& registry, entt::entity entity)
+    {
+		auto& component = registry.get<components::{{namespace}}::{{class_name}}>(entity);
+		{{widgets}}
+    }
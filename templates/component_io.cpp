@@ -0,0 +1,38 @@
+    // components::{{namespace}}::{{class_name}}
+    void load_{{id}} (million::api::EngineSetup* engine, entt::registry& registry, const void* tableptr, entt::entity entity)
+    {
+		{% if has_fields %}const auto& table = *reinterpret_cast<const toml::value*>(tableptr);
+		components::{{namespace}}::{{class_name}} component{};
+		{{loader}}
+		registry.emplace_or_replace<components::{{namespace}}::{{class_name}}>(entity, component);{% else %}registry.emplace_or_replace<components::{{namespace}}::{{class_name}}>(entity);{% endif %}
+    }
+
+    void save_{{id}} (million::api::EngineSetup* engine, const entt::registry& registry, void* tableptr, entt::entity entity)
+    {
+		auto& table = *reinterpret_cast<toml::value*>(tableptr);
+		{% if has_fields %}const auto& component = registry.get<components::{{namespace}}::{{class_name}}>(entity);
+		toml::table values;
+		{{saver}}
+		table = values;{% else %}table = toml::table{};{% endif %}
+    }
+
+    // Saves a component with in-range values, loads it back and checks that saving it again gives the same table
+    bool test_{{id}} (million::api::EngineSetup* engine)
+    {
+		try {
+			entt::registry registry;
+			const auto original = registry.create();
+			{% if has_fields %}components::{{namespace}}::{{class_name}} component{};
+			{{test_values}}
+			registry.emplace<components::{{namespace}}::{{class_name}}>(original, component);{% else %}registry.emplace<components::{{namespace}}::{{class_name}}>(original);{% endif %}
+			toml::value saved;
+			save_{{id}}(engine, registry, &saved, original);
+			const auto loaded = registry.create();
+			load_{{id}}(engine, registry, &saved, loaded);
+			toml::value resaved;
+			save_{{id}}(engine, registry, &resaved, loaded);
+			return saved == resaved;
+		} catch (const std::exception&) {
+			return false;
+		}
+    }
@@ -3,14 +3,46 @@
 #include <million/engine.hpp>
 #include <entt/entity/registry.hpp>
 #include <toml.hpp>
-
+#include <string>
+#include <vector>
+{% if has_warnings %}#include <spdlog/spdlog.h>
+#include <algorithm>
+{% endif %}{% if has_strings %}#include <cstring>
+{% endif %}{% if has_normalized %}#include <glm/geometric.hpp>
+{% endif %}{% if has_hashed_strings %}#include <mutex>
+#include <unordered_set>
+{% endif %}
 using namespace entt::literals;
 
 namespace init_{{name}} {
-
+{% if has_hashed_strings %}
+    // entt::hashed_string only points to its name, so loaded names are kept here for as long as the components can use them
+    const char* intern (const std::string& name)
+    {
+        static std::mutex mutex;
+        static std::unordered_set<std::string> names;
+        std::lock_guard<std::mutex> lock(mutex);
+        return names.insert(name).first->c_str();
+    }
+{% endif %}{% if has_floating %}
+    // Whole numbers are written as TOML integers, so floating point fields accept both
+    toml::floating as_floating (const toml::value& value)
+    {
+        return value.is_integer() ? toml::floating(toml::get<toml::integer>(value)) : toml::get<toml::floating>(value);
+    }
+{% endif %}
+{{component_io}}
     void register_components (million::api::internal::ModuleManager* engine)
     {
 {{components}}
     }
 
+    // Runs the load and save round-trip test of every component, returning the names of the ones that fail
+    std::vector<std::string> test_components (million::api::EngineSetup* engine)
+    {
+        std::vector<std::string> failed;
+{{component_tests}}
+        return failed;
+    }
+
 } // init_{{name}}
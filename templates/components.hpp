@@ -1,7 +1,8 @@
 // This file is autogenerated, do not edit!
 #pragma once
 #include <million/types.hpp>
-
+{{includes}}{% if has_arrays %}#include <array>
+{% endif %}
 {{pointer_declarations}}
 
 namespace components{% unless namespace == "" %}::{{namespace}}{% endunless %} {
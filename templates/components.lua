@@ -3,7 +3,11 @@ local ffi = require('ffi')
 ffi.cdef [[
 {{cdef}}
 ]]
+{{metatypes}}local components = {
+{{component_tables}}
+}
 local core = require('mm_core')
 core:register_components({
 {{component_map}}
-})
\ No newline at end of file
+})
+return components
\ No newline at end of file
@@ -0,0 +1,21 @@
+// This file is autogenerated, do not edit!
+#include <components/{{name}}.hpp>
+#include <million/engine.hpp>
+#include <entt/entity/registry.hpp>
+#include <imgui.h>
+#include <glm/geometric.hpp>
+#include <glm/gtc/type_ptr.hpp>
+#include <string>
+#include <type_traits>
+
+using namespace entt::literals;
+
+namespace init_{{name}} {
+
+{{inspectors}}
+    void register_inspectors (million::api::internal::ModuleManager* engine)
+    {
+{{registrations}}
+    }
+
+} // init_{{name}}
@@ -0,0 +1,5 @@
+-- This file is autogenerated, do not edit!
+-- Type annotations for lua-language-server, describing the structs declared by events.lua
+---@meta
+
+{{classes}}
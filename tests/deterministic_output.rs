@@ -0,0 +1,119 @@
+//! The generators must write byte-identical files on every run, so that regenerating
+//! code does not produce noisy diffs or invalidate build caches. Hash-based containers
+//! are seeded per process, so each run is a separate invocation of `mm`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const RUNS: usize = 5;
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/ordering");
+
+fn mm(args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_mm"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "mm {} failed:\n{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// An empty directory for the output of one run
+fn output_dir(name: &str, run: usize) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mm-{}-{}-{}", name, std::process::id(), run));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Contents of the files in `dir`, by file name
+fn read_outputs(dir: &Path) -> BTreeMap<String, Vec<u8>> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            (
+                path.file_name().unwrap().to_string_lossy().to_string(),
+                fs::read(&path).unwrap(),
+            )
+        })
+        .collect()
+}
+
+/// Runs `generate` into a fresh directory several times and checks that every run
+/// writes the same files with the same contents
+fn assert_stable(name: &str, generate: impl Fn(&str)) {
+    let mut first: Option<BTreeMap<String, Vec<u8>>> = None;
+    for run in 0..RUNS {
+        let dir = output_dir(name, run);
+        generate(dir.to_str().unwrap());
+        let outputs = read_outputs(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        match &first {
+            None => {
+                assert!(!outputs.is_empty(), "{} generated no files", name);
+                first = Some(outputs);
+            }
+            Some(first) => {
+                assert_eq!(
+                    first.keys().collect::<Vec<_>>(),
+                    outputs.keys().collect::<Vec<_>>(),
+                    "{} generated different files on run {}",
+                    name,
+                    run
+                );
+                for (file, contents) in first {
+                    assert!(
+                        outputs[file] == *contents,
+                        "{} generated a different {} on run {}",
+                        name,
+                        file,
+                        run
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn generate_all_is_deterministic() {
+    assert_stable("all", |dir| {
+        mm(&[
+            "generate",
+            "all",
+            "--inspectors",
+            "--metatypes",
+            dir,
+            FIXTURES,
+        ])
+    });
+}
+
+#[test]
+fn generate_components_is_deterministic() {
+    let source = format!("{}/game/components.toml", FIXTURES);
+    for generator in ["hpp", "lua", "cpp", "inspectors"] {
+        assert_stable(generator, |dir| {
+            mm(&[
+                "generate",
+                "components",
+                generator,
+                &source,
+                dir,
+                "-I",
+                FIXTURES,
+            ])
+        });
+    }
+}
+
+#[test]
+fn generate_schema_is_deterministic() {
+    assert_stable("schema", |dir| mm(&["generate", "schema", dir, FIXTURES]));
+}